@@ -0,0 +1,189 @@
+use serde_derive::{Serialize,Deserialize};
+use crate::{Color, Date, DatePiece, FormatParams, LocalDate, Task, Today, color::RESET, timestamp::DAY};
+
+/// Where the agenda splits the tasks, kept in the `[agenda]` table of the config
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Buckets {
+    /// Use the agenda instead of the categories for the conky output
+    #[serde(default)]
+    pub conky : bool,
+    /// Also list done tasks
+    #[serde(default)]
+    pub show_done : bool,
+    /// The last day of "This week" in days from today, the coming sunday by default
+    #[serde(default)]
+    pub this_week : Option<i64>,
+    /// Tasks due further away are left out, all of them are shown by default
+    #[serde(default)]
+    pub later : Option<i64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bucket {
+    Overdue,
+    Today,
+    Tomorrow,
+    ThisWeek,
+    Later,
+    NoDate,
+}
+
+impl Bucket {
+    pub const ALL : [Bucket; 6] = [Bucket::Overdue, Bucket::Today, Bucket::Tomorrow, Bucket::ThisWeek, Bucket::Later, Bucket::NoDate];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Bucket::Overdue => "Overdue",
+            Bucket::Today => "Today",
+            Bucket::Tomorrow => "Tomorrow",
+            Bucket::ThisWeek => "This week",
+            Bucket::Later => "Later",
+            Bucket::NoDate => "No date",
+        }
+    }
+}
+
+/// A task along with the list it comes from and that list's colors
+pub struct Item<'a> {
+    pub task : &'a Task,
+    pub colors : &'a FormatParams,
+    /// Put in front of the category, empty for a single list
+    pub list : &'a str,
+}
+
+/// Days from today until the task is due, negative once it's overdue
+pub fn days_left(task : &Task) -> Option<i64> {
+    let today = Date::from(LocalDate::today()).timestamp()?;
+    task.due.and_then(|d| d.timestamp()).map(|due| (due - today).div_euclid(DAY))
+}
+
+impl Buckets {
+    /// The bucket of a task due in `days`, `None` when it isn't shown
+    pub fn bucket(&self, days : Option<i64>) -> Option<Bucket> {
+        let today = Date::from(LocalDate::today()).timestamp().unwrap_or(0).div_euclid(DAY);
+        // the epoch was a thursday
+        let this_week = self.this_week.unwrap_or(6 - (today + 3).rem_euclid(7));
+
+        match days {
+            None => Some(Bucket::NoDate),
+            Some(d) if d < 0 => Some(Bucket::Overdue),
+            Some(0) => Some(Bucket::Today),
+            Some(1) => Some(Bucket::Tomorrow),
+            Some(d) if d <= this_week => Some(Bucket::ThisWeek),
+            Some(d) if self.later.is_none_or(|later| d <= later) => Some(Bucket::Later),
+            Some(_) => None,
+        }
+    }
+
+    /**
+        Sorts the items into the buckets, leaving out the empty ones.
+        Done tasks are only kept with `show_done`, and only until their due date has passed.
+    */
+    pub fn group<'a>(&self, items : Vec<Item<'a>>) -> Vec<(Bucket, Vec<Item<'a>>)> {
+        let mut groups : Vec<(Bucket, Vec<Item>)> = Bucket::ALL.iter().map(|b| (*b, Vec::new())).collect();
+
+        for item in items {
+            if item.task.done && !self.show_done {
+                continue;
+            }
+            match self.bucket(days_left(item.task)) {
+                Some(Bucket::Overdue) if item.task.done => {}
+                Some(bucket) => groups.iter_mut().find(|(b, _)| *b == bucket).unwrap().1.push(item),
+                None => {}
+            }
+        }
+
+        for (_, items) in groups.iter_mut() {
+            items.sort_by_key(|i| (
+                days_left(i.task),
+                i.task.done,
+                if i.task.priority == 0 { u8::MAX } else { i.task.priority },
+                i.task.name.clone(),
+            ));
+        }
+        groups.retain(|(_, items)| !items.is_empty());
+
+        groups
+    }
+}
+
+/// `- [x] Task (list/category)`
+fn label(item : &Item) -> String {
+    let mut s = String::from("- ");
+    if item.task.done {
+        s.push_str("[x] ");
+    }
+    s.push_str(&item.task.name);
+
+    let category = item.task.category.trim();
+    match (item.list.is_empty(), category.is_empty()) {
+        (true, true) => {}
+        (true, false) => s.push_str(&format!(" ({})", category)),
+        (false, true) => s.push_str(&format!(" ({})", item.list)),
+        (false, false) => s.push_str(&format!(" ({}/{})", item.list, category)),
+    }
+
+    s
+}
+
+/// When the task is due, relative to today
+fn when(task : &Task) -> String {
+    let (due, days) = match (task.due.and_then(|d| d.to_localdate()), days_left(task)) {
+        (Some(due), Some(days)) => (format!("{}/{}", due.day(), due.month() as i32), days),
+        _ => return String::new(),
+    };
+
+    match days {
+        d if d < -1 => format!("{} days late, {}", -d, due),
+        -1 => format!("1 day late, {}", due),
+        0 | 1 => due,
+        d => format!("in {} days, {}", d, due),
+    }
+}
+
+/// The agenda for conky, the headings use `heading`
+pub fn conky(groups : &[(Bucket, Vec<Item>)], heading : &Color) -> String {
+    let mut s = String::new();
+
+    for (bucket, items) in groups {
+        s.push_str(&format!("{}{}:\n", heading.to_conky(), bucket.title()));
+        for item in items {
+            s.push_str(&format!(" {}{}${{alignr}}{}\n", item.task.color(item.colors).to_conky(), label(item), when(item.task)));
+        }
+        s.push('\n');
+    }
+
+    s
+}
+
+/// The agenda for the terminal, colored with `ansi`
+pub fn text(groups : &[(Bucket, Vec<Item>)], heading : &Color, ansi : bool) -> String {
+    let mut s = String::new();
+
+    for (bucket, items) in groups {
+        if ansi {
+            s.push_str(&format!("{}{}{}\n", heading.to_ansi(), bucket.title(), RESET));
+        }
+        else {
+            s.push_str(&format!("{}\n", bucket.title()));
+        }
+
+        for item in items {
+            let mut line = label(item);
+            let when = when(item.task);
+            if !when.is_empty() {
+                line.push_str(&format!(" - {}", when));
+            }
+
+            if ansi {
+                s.push_str(&format!("  {}{}{}\n", item.task.color(item.colors).to_ansi(), line, RESET));
+            }
+            else {
+                s.push_str(&format!("  {}\n", line));
+            }
+        }
+        s.push('\n');
+    }
+
+    s
+}
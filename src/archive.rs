@@ -0,0 +1,85 @@
+use serde_derive::{Serialize,Deserialize};
+use crate::{Date, DatePiece, LocalDate, Task, Today, storage::Storage};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reason {
+    Done,
+    Removed,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArchivedTask {
+    pub reason : Reason,
+    /// When it was archived
+    pub date : Date,
+    pub task : Task,
+}
+impl ArchivedTask {
+    pub fn new(task : Task, reason : Reason) -> ArchivedTask {
+        ArchivedTask {
+            reason,
+            date : Date::from(LocalDate::today()),
+            task,
+        }
+    }
+
+    /// Like `Task::formatted`, with the reason and date it was archived
+    pub fn formatted(&self) -> String {
+        let what = match self.reason {
+            Reason::Done => "done",
+            Reason::Removed => "removed",
+        };
+        let date = match self.date.to_localdate() {
+            Some(d) => format!("{}/{}/{}", d.day(), d.month() as i32, d.year()),
+            None => String::from("?"),
+        };
+
+        format!("({}) {} [{} {}]", self.task.category, self.task.formatted(true), what, date)
+    }
+}
+
+/**
+    Tasks that were done or removed, kept by the storage of the list(next to a tasks file
+    as `tasks.archive.toml` for `tasks.toml`) so they can be looked up and restored.
+*/
+pub struct Archive {
+    pub tasks : Vec<ArchivedTask>,
+    storage : Box<dyn Storage>,
+}
+impl Archive {
+    /// The archive of a list, empty if there is none yet
+    pub fn open(storage : Box<dyn Storage>) -> Result<Archive, String> {
+        Ok(Archive {
+            tasks : storage.load_archive()?,
+            storage,
+        })
+    }
+
+    /// Indexes of the archived tasks with `query` in their name or categories, ignoring case
+    pub fn search(&self, query : &str) -> Result<Vec<usize>, String> {
+        self.storage.search_archive(query)
+    }
+
+    /// Takes a task out of the archive, see `TasksManager::restore`
+    pub fn take(&mut self, at : usize) -> Option<ArchivedTask> {
+        if at >= self.tasks.len() {
+            return None;
+        }
+
+        Some(self.tasks.remove(at))
+    }
+
+    /// Takes the latest archived task with one of the `ids` out of the archive, for undo and redo
+    pub fn remove_ids(&mut self, ids : &[String]) {
+        for id in ids {
+            if let Some(at) = self.tasks.iter().rposition(|a| &a.task.id == id) {
+                self.tasks.remove(at);
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        self.storage.save_archive(&self.tasks)
+    }
+}
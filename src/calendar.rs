@@ -0,0 +1,230 @@
+use crate::{Date, DatePiece, FormatParams, LocalDate, Task, Today, color::RESET, timestamp::{DAY, week_start}};
+
+const WEEKDAYS : [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// The days a calendar shows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Span {
+    /// A year and a month between 1 and 12
+    Month(i64, i8),
+    /// The week starting on the monday, in days since the epoch
+    Week(i64),
+}
+
+/// Today, in days since the epoch
+fn today() -> i64 {
+    day_of(Date::from(LocalDate::today()))
+}
+
+fn day_of(date : Date) -> i64 {
+    date.timestamp().unwrap_or(0).div_euclid(DAY)
+}
+
+fn date_of(day : i64) -> LocalDate {
+    Date::from_timestamp(day * DAY).to_localdate().unwrap()
+}
+
+/// Reads an offset like `+1` or `-2`
+fn offset(arg : &str) -> Option<i64> {
+    if arg.starts_with('+') || arg.starts_with('-') {
+        arg.parse().ok()
+    }
+    else {
+        None
+    }
+}
+
+/// The years a calendar can show, the dates outside of them can't be worked with
+const YEARS : (i64, i64) = (1, 9999);
+
+/// The first and last day a week can be shown for, in days since the epoch
+fn day_range() -> (i64, i64) {
+    let first = LocalDate::ymd(YEARS.0, Date::month_from_int(1), 1).map(Date::from).map(day_of);
+    let last = LocalDate::ymd(YEARS.1, Date::month_from_int(12), 31).map(Date::from).map(day_of);
+    (first.unwrap_or(0), last.unwrap_or(0))
+}
+
+impl Span {
+    /**
+        The month to show from the `--month` argument: nothing for the current month,
+        an offset like `+1` or `-1` from it, or a month as `m/y`.
+    */
+    pub fn month(arg : Option<&str>) -> Result<Span, String> {
+        let now = LocalDate::today();
+        let current = now.year() * 12 + now.month() as i64 - 1;
+
+        let month = match arg {
+            None => Some(current),
+            Some(arg) => match (offset(arg), arg.split_once('/')) {
+                (Some(n), _) => current.checked_add(n),
+                (None, Some((m, y))) => match (m.trim().parse::<i64>(), y.trim().parse::<i64>()) {
+                    (Ok(m), Ok(y)) if (1..=12).contains(&m) => y.checked_mul(12).map(|y| y + m - 1),
+                    _ => return Err(format!("invalid month '{}', expected m/y", arg)),
+                },
+                _ => return Err(format!("invalid month '{}', expected m/y or an offset like +1", arg)),
+            },
+        };
+        let month = match month.filter(|m| (YEARS.0..=YEARS.1).contains(&m.div_euclid(12))) {
+            Some(m) => m,
+            None => return Err(format!("invalid month '{}', the year has to be from {} to {}", arg.unwrap_or_default(), YEARS.0, YEARS.1)),
+        };
+
+        Ok(Span::Month(month.div_euclid(12), (month.rem_euclid(12) + 1) as i8))
+    }
+
+    /**
+        The week to show from the `--week` argument: nothing for the current week,
+        an offset like `+1` or `-1` from it, or a `d/m/y` date in the week.
+    */
+    pub fn week(arg : Option<&str>, parse_date : impl Fn(&str) -> Option<Date>) -> Result<Span, String> {
+        let day = match arg {
+            None => Some(today()),
+            Some(arg) => match (offset(arg), parse_date(arg)) {
+                (Some(n), _) => n.checked_mul(7).and_then(|n| today().checked_add(n)),
+                (None, Some(date)) if date.timestamp().is_some() => Some(day_of(date)),
+                _ => return Err(format!("invalid week '{}', expected d/m/y or an offset like +1", arg)),
+            },
+        };
+        let (first, last) = day_range();
+        let day = match day.filter(|d| (first + 7..=last - 7).contains(d)) {
+            Some(d) => d,
+            None => return Err(format!("invalid week '{}', the year has to be from {} to {}", arg.unwrap_or_default(), YEARS.0, YEARS.1)),
+        };
+
+        Ok(Span::Week(week_start(day)))
+    }
+
+    /// First and last day of the span, in days since the epoch
+    fn days(&self) -> (i64, i64) {
+        match *self {
+            Span::Month(year, month) => {
+                // `month` keeps the year in range, this is only a fallback for spans made by hand
+                let first_of = |year : i64, month : i8| LocalDate::ymd(year, Date::month_from_int(month as i32), 1)
+                    .map(|d| day_of(Date::from(d)))
+                    .unwrap_or(0);
+                let next = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+                (first_of(year, month), first_of(next.0, next.1) - 1)
+            }
+            Span::Week(monday) => (monday, monday + 6),
+        }
+    }
+
+    fn title(&self) -> String {
+        match *self {
+            Span::Month(year, month) => format!("{:?} {}", Date::month_from_int(month as i32), year),
+            Span::Week(monday) => {
+                let d = date_of(monday);
+                format!("Week of {}/{}/{}", d.day(), d.month() as i32, d.year())
+            }
+        }
+    }
+}
+
+/// One day of the grid
+struct Cell<'a> {
+    day : i64,
+    /// Days outside the month are left empty
+    shown : bool,
+    tasks : Vec<&'a Task>,
+}
+
+/// `s` cut or padded to exactly `width` characters
+fn fit(s : &str, width : usize) -> String {
+    let count = s.chars().count();
+    if count > width {
+        let mut cut : String = s.chars().take(width - 1).collect();
+        cut.push('~');
+        cut
+    }
+    else {
+        format!("{}{}", s, " ".repeat(width - count))
+    }
+}
+
+impl<'a> Cell<'a> {
+    /// The `line`th line of the cell, `lines` being how many it has besides the day number
+    fn line(&self, line : usize, lines : usize, width : usize, colors : &FormatParams, ansi : bool, week : bool) -> String {
+        if !self.shown {
+            return " ".repeat(width);
+        }
+
+        if line == 0 {
+            let d = date_of(self.day);
+            let mut label = if week {
+                format!("{} {}/{}", WEEKDAYS[(self.day - week_start(self.day)) as usize], d.day(), d.month() as i32)
+            }
+            else {
+                d.day().to_string()
+            };
+            if self.day == today() {
+                label.push_str(" today");
+            }
+            return fit(&label, width);
+        }
+
+        let index = line - 1;
+        let overflow = self.tasks.len() > lines;
+        if overflow && index == lines - 1 {
+            return fit(&format!("+{} more", self.tasks.len() - index), width);
+        }
+
+        match self.tasks.get(index) {
+            Some(task) => {
+                let text = fit(&format!("{} {}", if task.done { "x" } else { "-" }, task.name), width);
+                if ansi {
+                    format!("{}{}{}", task.color(colors).to_ansi(), text, RESET)
+                }
+                else {
+                    text
+                }
+            }
+            None => " ".repeat(width),
+        }
+    }
+}
+
+/**
+    Draws the span as a grid with the tasks due on each day.
+    Open tasks come first by priority, names longer than a cell are cut and the
+    tasks that don't fit are counted. `ansi` colors them like `colors` says.
+*/
+pub fn render(span : Span, tasks : &[Task], colors : &FormatParams, ansi : bool) -> String {
+    let (first, last) = span.days();
+    let week = matches!(span, Span::Week(_));
+    let (width, lines) = if week { (16, 10) } else { (12, 3) };
+
+    let cells : Vec<Cell> = (week_start(first)..=week_start(last) + 6)
+        .map(|day| {
+            let mut due : Vec<&Task> = tasks.iter().filter(|t| t.due.map(day_of) == Some(day)).collect();
+            due.sort_by_key(|t| (t.done, if t.priority == 0 { u8::MAX } else { t.priority }));
+            Cell { day, shown : day >= first && day <= last, tasks : due }
+        })
+        .collect();
+
+    let border = format!("+{}\n", format!("{}+", "-".repeat(width)).repeat(7));
+    let mut s = format!("{}\n", span.title());
+
+    if !week {
+        s.push_str(&border);
+        s.push('|');
+        for name in WEEKDAYS.iter() {
+            s.push_str(&format!("{}|", fit(name, width)));
+        }
+        s.push('\n');
+    }
+
+    for row in cells.chunks(7) {
+        s.push_str(&border);
+        for line in 0..=lines {
+            s.push('|');
+            for cell in row {
+                s.push_str(&cell.line(line, lines, width, colors, ansi, week));
+                s.push('|');
+            }
+            s.push('\n');
+        }
+    }
+    s.push_str(&border);
+
+    s
+}
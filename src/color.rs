@@ -0,0 +1,326 @@
+use serde_derive::{Serialize,Deserialize};
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+/**
+    A color used when printing tasks, independent of the output it ends up in.
+
+    It can be written in the config as:
+    - an empty string, meaning the output's default color
+    - `#rrggbb` (or the short `#rgb`), the `#` can be left out of `rrggbb` like conky allows
+      as long as there is a digit in it
+    - a named color such as `red` or `DarkOrange`, any of `NAMED` ignoring case
+    - a conky color slot, `color0` up to `color9`
+
+    Older configs stored the raw conky text (like `color red`), so a leading
+    `color ` is accepted and ignored.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Color {
+    #[default]
+    Default,
+    Rgb(u8, u8, u8),
+    Named(String),
+    Slot(u8),
+}
+
+/// Ends what `Color::to_ansi` started, back to the terminal's colors
+pub const RESET : &str = "\x1b[0m";
+
+/**
+    The names a color can be given by, the X11 names conky takes (without the numbered variants
+    like `gray50`) in lowercase. The first ones use their css values, which differ from X11 for a few.
+*/
+const NAMED : [(&str, (u8, u8, u8)); 145] = [
+    ("black", (0, 0, 0)),
+    ("white", (255, 255, 255)),
+    ("red", (255, 0, 0)),
+    ("green", (0, 128, 0)),
+    ("lime", (0, 255, 0)),
+    ("blue", (0, 0, 255)),
+    ("yellow", (255, 255, 0)),
+    ("cyan", (0, 255, 255)),
+    ("magenta", (255, 0, 255)),
+    ("gray", (128, 128, 128)),
+    ("grey", (128, 128, 128)),
+    ("orange", (255, 165, 0)),
+    ("purple", (128, 0, 128)),
+    ("pink", (255, 192, 203)),
+    ("brown", (165, 42, 42)),
+    ("navy", (0, 0, 128)),
+    ("aliceblue", (240, 248, 255)),
+    ("antiquewhite", (250, 235, 215)),
+    ("aquamarine", (127, 255, 212)),
+    ("azure", (240, 255, 255)),
+    ("beige", (245, 245, 220)),
+    ("bisque", (255, 228, 196)),
+    ("blanchedalmond", (255, 235, 205)),
+    ("blueviolet", (138, 43, 226)),
+    ("burlywood", (222, 184, 135)),
+    ("cadetblue", (95, 158, 160)),
+    ("chartreuse", (127, 255, 0)),
+    ("chocolate", (210, 105, 30)),
+    ("coral", (255, 127, 80)),
+    ("cornflowerblue", (100, 149, 237)),
+    ("cornsilk", (255, 248, 220)),
+    ("darkblue", (0, 0, 139)),
+    ("darkcyan", (0, 139, 139)),
+    ("darkgoldenrod", (184, 134, 11)),
+    ("darkgray", (169, 169, 169)),
+    ("darkgreen", (0, 100, 0)),
+    ("darkgrey", (169, 169, 169)),
+    ("darkkhaki", (189, 183, 107)),
+    ("darkmagenta", (139, 0, 139)),
+    ("darkolivegreen", (85, 107, 47)),
+    ("darkorange", (255, 140, 0)),
+    ("darkorchid", (153, 50, 204)),
+    ("darkred", (139, 0, 0)),
+    ("darksalmon", (233, 150, 122)),
+    ("darkseagreen", (143, 188, 143)),
+    ("darkslateblue", (72, 61, 139)),
+    ("darkslategray", (47, 79, 79)),
+    ("darkslategrey", (47, 79, 79)),
+    ("darkturquoise", (0, 206, 209)),
+    ("darkviolet", (148, 0, 211)),
+    ("debianred", (215, 7, 81)),
+    ("deeppink", (255, 20, 147)),
+    ("deepskyblue", (0, 191, 255)),
+    ("dimgray", (105, 105, 105)),
+    ("dimgrey", (105, 105, 105)),
+    ("dodgerblue", (30, 144, 255)),
+    ("firebrick", (178, 34, 34)),
+    ("floralwhite", (255, 250, 240)),
+    ("forestgreen", (34, 139, 34)),
+    ("gainsboro", (220, 220, 220)),
+    ("ghostwhite", (248, 248, 255)),
+    ("gold", (255, 215, 0)),
+    ("goldenrod", (218, 165, 32)),
+    ("greenyellow", (173, 255, 47)),
+    ("honeydew", (240, 255, 240)),
+    ("hotpink", (255, 105, 180)),
+    ("indianred", (205, 92, 92)),
+    ("ivory", (255, 255, 240)),
+    ("khaki", (240, 230, 140)),
+    ("lavender", (230, 230, 250)),
+    ("lavenderblush", (255, 240, 245)),
+    ("lawngreen", (124, 252, 0)),
+    ("lemonchiffon", (255, 250, 205)),
+    ("lightblue", (173, 216, 230)),
+    ("lightcoral", (240, 128, 128)),
+    ("lightcyan", (224, 255, 255)),
+    ("lightgoldenrod", (238, 221, 130)),
+    ("lightgoldenrodyellow", (250, 250, 210)),
+    ("lightgray", (211, 211, 211)),
+    ("lightgreen", (144, 238, 144)),
+    ("lightgrey", (211, 211, 211)),
+    ("lightpink", (255, 182, 193)),
+    ("lightsalmon", (255, 160, 122)),
+    ("lightseagreen", (32, 178, 170)),
+    ("lightskyblue", (135, 206, 250)),
+    ("lightslateblue", (132, 112, 255)),
+    ("lightslategray", (119, 136, 153)),
+    ("lightslategrey", (119, 136, 153)),
+    ("lightsteelblue", (176, 196, 222)),
+    ("lightyellow", (255, 255, 224)),
+    ("limegreen", (50, 205, 50)),
+    ("linen", (250, 240, 230)),
+    ("maroon", (176, 48, 96)),
+    ("mediumaquamarine", (102, 205, 170)),
+    ("mediumblue", (0, 0, 205)),
+    ("mediumorchid", (186, 85, 211)),
+    ("mediumpurple", (147, 112, 219)),
+    ("mediumseagreen", (60, 179, 113)),
+    ("mediumslateblue", (123, 104, 238)),
+    ("mediumspringgreen", (0, 250, 154)),
+    ("mediumturquoise", (72, 209, 204)),
+    ("mediumvioletred", (199, 21, 133)),
+    ("midnightblue", (25, 25, 112)),
+    ("mintcream", (245, 255, 250)),
+    ("mistyrose", (255, 228, 225)),
+    ("moccasin", (255, 228, 181)),
+    ("navajowhite", (255, 222, 173)),
+    ("navyblue", (0, 0, 128)),
+    ("oldlace", (253, 245, 230)),
+    ("olivedrab", (107, 142, 35)),
+    ("orangered", (255, 69, 0)),
+    ("orchid", (218, 112, 214)),
+    ("palegoldenrod", (238, 232, 170)),
+    ("palegreen", (152, 251, 152)),
+    ("paleturquoise", (175, 238, 238)),
+    ("palevioletred", (219, 112, 147)),
+    ("papayawhip", (255, 239, 213)),
+    ("peachpuff", (255, 218, 185)),
+    ("peru", (205, 133, 63)),
+    ("plum", (221, 160, 221)),
+    ("powderblue", (176, 224, 230)),
+    ("rosybrown", (188, 143, 143)),
+    ("royalblue", (65, 105, 225)),
+    ("saddlebrown", (139, 69, 19)),
+    ("salmon", (250, 128, 114)),
+    ("sandybrown", (244, 164, 96)),
+    ("seagreen", (46, 139, 87)),
+    ("seashell", (255, 245, 238)),
+    ("sienna", (160, 82, 45)),
+    ("skyblue", (135, 206, 235)),
+    ("slateblue", (106, 90, 205)),
+    ("slategray", (112, 128, 144)),
+    ("slategrey", (112, 128, 144)),
+    ("snow", (255, 250, 250)),
+    ("springgreen", (0, 255, 127)),
+    ("steelblue", (70, 130, 180)),
+    ("tan", (210, 180, 140)),
+    ("thistle", (216, 191, 216)),
+    ("tomato", (255, 99, 71)),
+    ("turquoise", (64, 224, 208)),
+    ("violet", (238, 130, 238)),
+    ("violetred", (208, 32, 144)),
+    ("wheat", (245, 222, 179)),
+    ("whitesmoke", (245, 245, 245)),
+    ("yellowgreen", (154, 205, 50)),
+];
+
+impl Color {
+    /// The rgb value of the color, if it has a known one
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        match self {
+            Color::Rgb(r, g, b) => Some((*r, *g, *b)),
+            Color::Named(name) => NAMED.iter().find(|(n, _)| n == name).map(|(_, c)| *c),
+            _ => None,
+        }
+    }
+
+    /// A conky variable switching to this color, like `${color red}`
+    pub fn to_conky(&self) -> String {
+        match self {
+            Color::Default => String::from("${color}"),
+            Color::Rgb(r, g, b) => format!("${{color #{:02x}{:02x}{:02x}}}", r, g, b),
+            Color::Named(name) => format!("${{color {}}}", name),
+            Color::Slot(n) => format!("${{color{}}}", n),
+        }
+    }
+
+    /**
+        An ANSI escape sequence switching the foreground to this color.
+
+        Conky slots are user defined, so they map to the matching entry of the
+        terminal's 256 color palette.
+    */
+    pub fn to_ansi(&self) -> String {
+        match (self, self.rgb()) {
+            (Color::Default, _) => String::from("\x1b[39m"),
+            (Color::Slot(n), _) => format!("\x1b[38;5;{}m", n),
+            (_, Some((r, g, b))) => format!("\x1b[38;2;{};{};{}m", r, g, b),
+            (_, None) => String::from("\x1b[39m"),
+        }
+    }
+
+    /// A value usable for the css `color` property
+    pub fn to_css(&self) -> String {
+        match self {
+            Color::Default => String::from("inherit"),
+            Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            Color::Named(name) => name.clone(),
+            Color::Slot(n) => format!("var(--color{})", n),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Color, String> {
+        let s = s.trim();
+        let s = s.strip_prefix("color ").unwrap_or(s).trim();
+
+        if s.is_empty() || s == "color" {
+            return Ok(Color::Default);
+        }
+
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_hex(hex).ok_or_else(|| format!("invalid hex color '{}', expected #rrggbb", s));
+        }
+
+        if let Some(n) = s.strip_prefix("color") {
+            return match n.parse::<u8>() {
+                Ok(n) if n < 10 => Ok(Color::Slot(n)),
+                _ => Err(format!("invalid conky color slot '{}', expected color0 to color9", s)),
+            };
+        }
+
+        let name = s.to_lowercase();
+        if NAMED.iter().any(|(n, _)| *n == name) {
+            return Ok(Color::Named(name));
+        }
+
+        // conky's bare `rrggbb`, with a digit so a misspelled name like `fed` isn't taken for one
+        if s.len() == 6 && s.chars().any(|c| c.is_ascii_digit()) {
+            if let Some(c) = parse_hex(s) {
+                return Ok(c);
+            }
+        }
+
+        Err(format!("unknown color '{}', expected a color name, #rrggbb or color0 to color9", s))
+    }
+}
+
+/// `rrggbb` or `rgb` hex digits, without the `#`
+fn parse_hex(hex : &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits = match hex.len() {
+        3 => hex.chars().flat_map(|c| vec![c, c]).collect(),
+        6 => String::from(hex),
+        _ => return None,
+    };
+    let channel = |i : usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+
+    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s : String) -> Result<Color, String> {
+        s.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(c : Color) -> String {
+        c.to_string()
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Color::Default => Ok(()),
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Color::Named(name) => write!(f, "{}", name),
+            Color::Slot(n) => write!(f, "color{}", n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_config_forms() {
+        assert_eq!("".parse(), Ok(Color::Default));
+        assert_eq!("#0f0".parse(), Ok(Color::Rgb(0, 255, 0)));
+        assert_eq!("color ff0000".parse(), Ok(Color::Rgb(255, 0, 0)));
+        assert_eq!("DarkOrange".parse(), Ok(Color::Named(String::from("darkorange"))));
+        assert_eq!("color3".parse(), Ok(Color::Slot(3)));
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert!("rde".parse::<Color>().is_err());
+        assert!("fed".parse::<Color>().is_err());
+        assert!("facade".parse::<Color>().is_err());
+        assert!("color12".parse::<Color>().is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use serde_derive::{Serialize,Deserialize};
+use std::{collections::BTreeMap, fs};
+use crate::{FormatParams, agenda::Buckets, paths::{self, Resolved}, spreadsheet::Columns};
+
+/**
+    User settings that are not task data, kept in their own file so the tasks
+    file can be shared or synced without dragging the colors along.
+*/
+#[derive(Serialize,Deserialize)]
+pub struct Config {
+    /// The list used when neither `--file` nor `--list` are given
+    #[serde(default)]
+    pub default_list : Option<String>,
+    /// Commit the tasks file to a git repository on every save, see `History`
+    #[serde(default)]
+    pub git_history : bool,
+    /// Keep a journal of the changes next to the tasks file for `undo` and `redo`
+    #[serde(default = "enabled")]
+    pub journal : bool,
+    /// Done tasks are moved to the archive this many days after they were done
+    /// (by their due date for tasks done before completion times were kept)
+    #[serde(default = "archive_after_days")]
+    pub archive_after_days : i64,
+    /// Archive done tasks on every save, not just with `--archive_done`
+    #[serde(default = "enabled")]
+    pub auto_archive : bool,
+    /// Exported to on every save as iCalendar, for calendar apps to subscribe to
+    #[serde(default)]
+    pub ics_file : Option<String>,
+    /// Add a VEVENT for every task with a due date to `ics_file`
+    #[serde(default)]
+    pub ics_events : bool,
+    /// Keep the default tasks file and new lists in sqlite databases instead of toml files
+    #[serde(default)]
+    pub sqlite : bool,
+    /// `None` keeps using the colors from the tasks file
+    #[serde(default)]
+    pub colors : Option<FormatParams>,
+    /// How the agenda groups the tasks by their due date
+    #[serde(default)]
+    pub agenda : Buckets,
+    /// The columns of csv files for `import` and `export`
+    #[serde(default)]
+    pub csv : Columns,
+    /// Named task lists and the files they are kept in
+    #[serde(default)]
+    pub lists : BTreeMap<String, String>,
+}
+fn enabled() -> bool {
+    true
+}
+fn archive_after_days() -> i64 {
+    14
+}
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            default_list : None,
+            git_history : false,
+            journal : true,
+            archive_after_days : archive_after_days(),
+            auto_archive : true,
+            ics_file : None,
+            ics_events : false,
+            sqlite : false,
+            colors : None,
+            agenda : Buckets::default(),
+            csv : Columns::default(),
+            lists : BTreeMap::new(),
+        }
+    }
+}
+impl Config {
+    /// `$XDG_CONFIG_HOME/cool_organizer/config.toml`
+    pub fn default_path() -> String {
+        paths::config_file(None).path
+    }
+
+    /// Loads the config file, `None` if there is no such file
+    pub fn load(path : &str) -> Result<Option<Config>, String> {
+        let file = match fs::read_to_string(path) {
+            Ok(f) => f,
+            Err(_) => return Ok(None),
+        };
+
+        toml::from_str(&file)
+            .map(Some)
+            .map_err(|e| format!("couldn't load {}: {}", path, e))
+    }
+
+    /// Extension of the tasks files that aren't given a path, `db` with `sqlite`
+    pub fn extension(&self) -> &'static str {
+        if self.sqlite { "db" } else { "toml" }
+    }
+
+    /// The file of a named list
+    pub fn list_file(&self, name : &str, reason : &str) -> Result<Resolved, String> {
+        match self.lists.get(name) {
+            Some(path) => Ok(Resolved {
+                path : path.clone(),
+                reason : format!("{} (list '{}')", reason, name),
+            }),
+            None => Err(format!("no list named '{}', create it with `list new {}`", name, name)),
+        }
+    }
+
+    /// The default list, if there is one
+    pub fn default_list_file(&self) -> Result<Option<Resolved>, String> {
+        match &self.default_list {
+            Some(name) => self.list_file(name, "the default list in the config").map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /**
+        Registers a new list, kept at `path` or at `$XDG_DATA_HOME/cool_organizer/<name>.toml`
+        (`.db` with `sqlite`) when no path is given. Returns the path of the list.
+    */
+    pub fn add_list(&mut self, name : &str, path : Option<&str>) -> Result<String, String> {
+        if name.is_empty() || name.contains(|c : char| c == '/' || c == ',' || c.is_whitespace()) {
+            return Err(format!("invalid list name '{}'", name));
+        }
+        if self.lists.contains_key(name) {
+            return Err(format!("list '{}' already exists", name));
+        }
+
+        let path = match path {
+            Some(p) => String::from(p),
+            None => format!("{}/cool_organizer/{}.{}", paths::data_home(), name, self.extension()),
+        };
+        self.lists.insert(String::from(name), path.clone());
+
+        Ok(path)
+    }
+
+    pub fn save(&self, path : &str) -> Result<(), String> {
+        let toml = toml::to_string(self).map_err(|e| e.to_string())?;
+
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+        }
+        fs::write(path, toml).map_err(|e| format!("couldn't save {}: {}", path, e))
+    }
+}
@@ -0,0 +1,80 @@
+use serde_derive::Serialize;
+use std::{fmt, path::Path};
+use crate::{Task, TasksManager, markdown, todotxt, upgrade};
+
+/// The format of a tasks file, picked from its extension or the `--format` argument
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Toml,
+    /// One task per line, see `todotxt`. The colors can only be kept in the config file.
+    TodoTxt,
+    /// A checklist under headings, see `markdown`. The colors can only be kept in the config file.
+    Markdown,
+}
+
+/// What gets written to the tasks file when the colors are kept in a config file
+#[derive(Serialize)]
+struct TasksOnly<'a> {
+    tasks : &'a [Task],
+}
+
+impl Format {
+    pub const NAMES : [&'static str; 3] = ["toml", "todotxt", "markdown"];
+
+    pub fn from_name(name : &str) -> Result<Format, String> {
+        match name.to_lowercase().as_str() {
+            "toml" => Ok(Format::Toml),
+            "todotxt" | "txt" => Ok(Format::TodoTxt),
+            "markdown" | "md" => Ok(Format::Markdown),
+            _ => Err(format!("unknown format '{}', expected one of: {}", name, Format::NAMES.join(", "))),
+        }
+    }
+
+    /// Detects the format from the file extension
+    pub fn from_path(path : &str) -> Result<Format, String> {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some(ext) => Format::from_name(ext)
+                .map_err(|_| format!("unknown file extension '.{}' for {}, use --format to pick one of: {}", ext, path, Format::NAMES.join(", "))),
+            None => Err(format!("can't tell the format of {} without an extension, use --format to pick one of: {}", path, Format::NAMES.join(", "))),
+        }
+    }
+
+    /// Parses the content of a tasks file, an empty file gives an empty manager
+    pub fn parse(&self, text : &str) -> Result<TasksManager, String> {
+        match self {
+            Format::Toml => {
+                let mut value = text.parse::<toml::Value>().map_err(|e| e.to_string())?;
+                upgrade::upgrade(&mut value)?;
+                value.try_into().map_err(|e| e.to_string())
+            }
+            Format::TodoTxt => Ok(TasksManager { tasks : todotxt::parse(text)?, ..TasksManager::default() }),
+            Format::Markdown => Ok(TasksManager { tasks : markdown::parse(text)?, ..TasksManager::default() }),
+        }
+    }
+
+    /// Writes the tasks, leaving out the colors if they are kept in a config file
+    pub fn write(&self, tasks : &TasksManager) -> Result<String, String> {
+        match self {
+            Format::Toml => match tasks.config_path {
+                Some(_) => toml::to_string(&TasksOnly { tasks : &tasks.tasks }),
+                None => toml::to_string(tasks),
+            }
+            // Plain values have to come before the tables, so it goes first
+            .map(|t| format!("format_version = {}\n\n{}", upgrade::FORMAT_VERSION, t))
+            .map_err(|e| e.to_string()),
+            Format::TodoTxt => Ok(todotxt::write(&tasks.tasks)),
+            Format::Markdown => Ok(markdown::write(&tasks.tasks)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Toml => write!(f, "toml"),
+            Format::TodoTxt => write!(f, "todotxt"),
+            Format::Markdown => write!(f, "markdown"),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::{path::Path, process::Command};
+
+/**
+    Git history of a tasks file.
+
+    Every file gets its own repository next to it(`.tasks.toml.git` for `tasks.toml`),
+    so it never mixes with a repository the file might already be in.
+*/
+pub struct History {
+    git_dir : String,
+    work_tree : String,
+    file : String,
+}
+impl History {
+    pub fn for_file(path : &str) -> History {
+        let path = Path::new(path);
+        let dir = match path.parent() {
+            Some(d) if !d.as_os_str().is_empty() => d.to_string_lossy().into_owned(),
+            _ => String::from("."),
+        };
+        let file = path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+
+        History {
+            git_dir : format!("{}/.{}.git", dir, file),
+            work_tree : dir,
+            file,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        Path::new(&self.git_dir).exists()
+    }
+
+    fn git(&self, args : &[&str]) -> Result<String, String> {
+        let out = Command::new("git")
+            .arg("--git-dir").arg(&self.git_dir)
+            .arg("--work-tree").arg(&self.work_tree)
+            .args(args)
+            .output()
+            .map_err(|e| format!("couldn't run git: {}", e))?;
+
+        if out.status.success() {
+            Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+        }
+        else {
+            Err(format!("git {} failed: {}", args[0], String::from_utf8_lossy(&out.stderr).trim()))
+        }
+    }
+
+    /// Commits the current state of the file, creating the repository if needed
+    pub fn commit(&self, message : &str) -> Result<(), String> {
+        if !self.exists() {
+            self.git(&["init", "--quiet"])?;
+        }
+        // Without a git identity fall back to the user name, the history is about who did what
+        if self.git(&["config", "user.name"]).is_err() {
+            let user = std::env::var("USER").unwrap_or_else(|_| String::from("cool_organizer"));
+            self.git(&["config", "user.name", &user])?;
+            self.git(&["config", "user.email", &format!("{}@localhost", user)])?;
+        }
+
+        self.git(&["add", "--", &self.file])?;
+        // Nothing to commit when the file didn't actually change
+        if self.git(&["diff", "--cached", "--quiet"]).is_ok() && self.git(&["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok() {
+            return Ok(());
+        }
+        self.git(&["commit", "--quiet", "-m", message]).map(|_| ())
+    }
+
+    /// The last `count` commits, newest first
+    pub fn log(&self, count : usize) -> Result<String, String> {
+        self.check()?;
+        self.git(&["log", &format!("-{}", count), "--date=format:%d/%m/%Y %H:%M", "--format=%h %ad %an%n    %s"])
+    }
+
+    /// Reverts the changes made by `rev`(the last commit by default), returning its summary
+    pub fn undo(&self, rev : &str) -> Result<String, String> {
+        self.check()?;
+        let summary = self.git(&["log", "-1", "--format=%s", rev])?;
+
+        if let Err(e) = self.git(&["revert", "--no-edit", rev]) {
+            let _ = self.git(&["revert", "--abort"]);
+            return Err(e);
+        }
+
+        Ok(String::from(summary.trim()))
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.exists() {
+            Ok(())
+        }
+        else {
+            Err(format!("no history for {}/{}, turn on `git_history` in the config", self.work_tree, self.file))
+        }
+    }
+}
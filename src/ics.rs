@@ -0,0 +1,311 @@
+use crate::{Date, DatePiece, Task, id, timestamp};
+
+/// The iCalendar PRIORITY of a task priority, 1 is the highest in both
+fn priority(p : u8) -> Option<u8> {
+    match p {
+        1 => Some(1),
+        2 => Some(5),
+        3 => Some(9),
+        _ => None,
+    }
+}
+
+/// The task priority of an iCalendar PRIORITY, 1-4 are high, 5 medium and 6-9 low
+fn from_priority(p : u8) -> u8 {
+    match p {
+        1..=4 => 1,
+        5 => 2,
+        6..=9 => 3,
+        _ => 0,
+    }
+}
+
+/// `value` with the characters iCalendar text can't hold escaped
+fn escape(value : &str) -> String {
+    value.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// A date as `yyyymmdd`
+fn date(d : Date) -> Option<String> {
+    d.to_localdate().map(|d| format!("{:04}{:02}{:02}", d.year(), d.month() as i32, d.day()))
+}
+
+/// Adds a content line, folded at 75 bytes like the spec asks
+fn push_line(s : &mut String, line : &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            s.push_str("\r\n ");
+            width = 1;
+        }
+        s.push(c);
+        width += c.len_utf8();
+    }
+    s.push_str("\r\n");
+}
+
+/// The lines describing a task shared by its VTODO and VEVENT
+fn task_lines(task : &Task, now : i64) -> Vec<String> {
+    let mut lines = vec![
+        format!("DTSTAMP:{}", timestamp::compact(task.modified.unwrap_or(now))),
+        format!("SUMMARY:{}", escape(task.name.trim())),
+    ];
+
+    let categories : Vec<String> = [&task.category, &task.sub_category].iter()
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .map(escape)
+        .collect();
+    if !categories.is_empty() {
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    if let Some(p) = priority(task.priority) {
+        lines.push(format!("PRIORITY:{}", p));
+    }
+    if let Some(created) = task.created {
+        lines.push(format!("CREATED:{}", timestamp::compact(created)));
+    }
+    if let Some(modified) = task.modified {
+        lines.push(format!("LAST-MODIFIED:{}", timestamp::compact(modified)));
+    }
+
+    lines
+}
+
+/**
+    The tasks as an iCalendar file with a VTODO per task, and with `events` a
+    VEVENT for each task with a due date as well(for calendars that don't show to-dos).
+    The task id is the UID of the VTODO, so the file can be imported back without duplicates.
+*/
+pub fn export(tasks : &[Task], events : bool) -> String {
+    let now = timestamp::now();
+    let mut s = String::new();
+
+    push_line(&mut s, "BEGIN:VCALENDAR");
+    push_line(&mut s, "VERSION:2.0");
+    push_line(&mut s, "PRODID:-//cool_organizer//tasks//EN");
+
+    for task in tasks {
+        push_line(&mut s, "BEGIN:VTODO");
+        push_line(&mut s, &format!("UID:{}", task.id));
+        for line in task_lines(task, now) {
+            push_line(&mut s, &line);
+        }
+        if let Some(due) = task.due.and_then(date) {
+            push_line(&mut s, &format!("DUE;VALUE=DATE:{}", due));
+        }
+        if task.done {
+            push_line(&mut s, "STATUS:COMPLETED");
+            if let Some(completed) = task.completed {
+                push_line(&mut s, &format!("COMPLETED:{}", timestamp::compact(completed)));
+            }
+        }
+        else {
+            push_line(&mut s, "STATUS:NEEDS-ACTION");
+        }
+        push_line(&mut s, "END:VTODO");
+    }
+
+    if events {
+        for task in tasks {
+            let (start, end) = match task.due.and_then(|d| d.timestamp()) {
+                Some(t) => (Date::from_timestamp(t), Date::from_timestamp(t + 60 * 60 * 24)),
+                None => continue,
+            };
+
+            push_line(&mut s, "BEGIN:VEVENT");
+            push_line(&mut s, &format!("UID:{}-event", task.id));
+            for line in task_lines(task, now) {
+                push_line(&mut s, &line);
+            }
+            push_line(&mut s, &format!("DTSTART;VALUE=DATE:{}", date(start).unwrap_or_default()));
+            push_line(&mut s, &format!("DTEND;VALUE=DATE:{}", date(end).unwrap_or_default()));
+            push_line(&mut s, "TRANSP:TRANSPARENT");
+            push_line(&mut s, "END:VEVENT");
+        }
+    }
+
+    push_line(&mut s, "END:VCALENDAR");
+
+    s
+}
+
+/// Splits an escaped text value on the unescaped `sep`, unescaping the parts
+fn unescape_split(value : &str, sep : char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => parts.last_mut().unwrap().push('\n'),
+                Some(c) => parts.last_mut().unwrap().push(c),
+                None => {}
+            },
+            c if c == sep => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    parts
+}
+
+fn unescape(value : &str) -> String {
+    unescape_split(value, '\0').concat()
+}
+
+/// A `yyyymmdd` date, or a `yyyymmddThhmmss` date-time(read as utc) and its timestamp
+fn parse_date_time(value : &str) -> Option<(Date, i64)> {
+    let time = match value.len() {
+        8 => Date::from_iso(&format!("{}-{}-{}", value.get(0..4)?, value.get(4..6)?, value.get(6..8)?))?.timestamp()?,
+        _ => timestamp::parse_compact(value)?,
+    };
+
+    Some((Date::from_timestamp(time), time))
+}
+
+/// Splits a content line into its name and value, dropping the parameters(the colons in quoted ones are skipped)
+fn split_line(line : &str) -> Option<(String, &str)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        *c == ':' && !quoted
+    })?.0;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let name = head.split(';').next().unwrap_or(head);
+
+    Some((name.to_uppercase(), value))
+}
+
+/**
+    Reads the VTODO components of an iCalendar file as tasks.
+    The UID becomes the task id, items without one get an id made from their
+    summary and due date so importing the same file twice doesn't duplicate them.
+*/
+pub fn parse(text : &str) -> Result<Vec<Task>, String> {
+    // Unfold the lines continued with a leading space or tab first
+    let mut lines : Vec<(usize, String)> = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some((_, last))) => last.push_str(rest),
+            _ => lines.push((n + 1, String::from(line))),
+        }
+    }
+
+    let mut tasks = Vec::new();
+    let mut components : Vec<String> = Vec::new();
+    let mut task : Option<(usize, Task, Option<String>, Option<String>)> = None;
+
+    for (n, line) in lines.iter() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (name, value) = split_line(line).ok_or(format!("line {}: expected NAME:value, got '{}'", n, line))?;
+
+        match name.as_str() {
+            "BEGIN" => {
+                let component = value.trim().to_uppercase();
+                if component == "VTODO" {
+                    task = Some((*n, Task::imported(""), None, None));
+                }
+                components.push(component);
+            }
+            "END" => {
+                let component = value.trim().to_uppercase();
+                if components.pop().as_deref() != Some(component.as_str()) {
+                    return Err(format!("line {}: END:{} without a matching BEGIN", n, component));
+                }
+                if component == "VTODO" {
+                    let (start, mut t, uid, due) = task.take().unwrap();
+                    if t.name.trim().is_empty() {
+                        return Err(format!("line {}: VTODO without a SUMMARY", start));
+                    }
+                    t.id = uid.unwrap_or_else(|| id::id_from(&[&t.name, due.as_deref().unwrap_or_default()]));
+                    tasks.push(t);
+                }
+            }
+            _ if components.last().map(|c| c.as_str()) == Some("VTODO") => {
+                let (_, t, uid, due) = task.as_mut().unwrap();
+                let time = || parse_date_time(value.trim()).ok_or(format!("line {}: invalid date '{}' for {}", n, value, name));
+
+                match name.as_str() {
+                    "UID" => *uid = Some(unescape(value.trim())),
+                    "SUMMARY" => t.name = unescape(value),
+                    "CATEGORIES" => {
+                        let mut categories = unescape_split(value, ',').into_iter().map(|c| String::from(c.trim()));
+                        t.category = categories.next().unwrap_or_default();
+                        t.sub_category = categories.next().unwrap_or_default();
+                    }
+                    "PRIORITY" => t.priority = from_priority(value.trim().parse().unwrap_or(0)),
+                    "DUE" => {
+                        t.due = Some(time()?.0);
+                        *due = Some(String::from(value.trim()));
+                    }
+                    "STATUS" => t.done = value.trim().eq_ignore_ascii_case("COMPLETED"),
+                    "COMPLETED" => t.completed = Some(time()?.1),
+                    "CREATED" => t.created = Some(time()?.1),
+                    "LAST-MODIFIED" => t.modified = Some(time()?.1),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match components.last() {
+        Some(c) => Err(format!("BEGIN:{} is never closed", c)),
+        None => Ok(tasks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tasks() -> Vec<Task> {
+        let mut done = Task::new("b; with, escapes\nand a line break that is long enough to be folded over two lines");
+        done.done = true;
+        done.priority = 3;
+        done.completed = Some(done.created.unwrap() + 60);
+        vec![
+            Task {
+                category : String::from("work"),
+                sub_category : String::from("mail"),
+                priority : 1,
+                due : Date::from_iso("2026-10-25"),
+                ..Task::new("a")
+            },
+            done,
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let tasks = tasks();
+        assert!(parse(&export(&tasks, false)).unwrap() == tasks);
+        // The events are left out when reading
+        assert!(parse(&export(&tasks, true)).unwrap() == tasks);
+    }
+
+    #[test]
+    fn items_without_uid_get_the_same_id() {
+        let text = "BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY:a\nDUE;VALUE=DATE:20261025\nEND:VTODO\nEND:VCALENDAR\n";
+        let first = parse(text).unwrap();
+        assert_eq!(first[0].due, Date::from_iso("2026-10-25"));
+        assert_eq!(first[0].id, parse(text).unwrap()[0].id);
+    }
+
+    #[test]
+    fn bad_files_fail() {
+        assert!(parse("BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY:a\nEND:VCALENDAR\n").is_err());
+        assert!(parse("BEGIN:VCALENDAR\nBEGIN:VTODO\nEND:VTODO\nEND:VCALENDAR\n").is_err());
+        assert!(parse("BEGIN:VTODO\nSUMMARY:a\nDUE:20261301\nEND:VTODO\n").is_err());
+        assert!(parse("BEGIN:VTODO\nSUMMARY:a\n").is_err());
+    }
+}
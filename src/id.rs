@@ -0,0 +1,67 @@
+use std::{process, sync::atomic::{AtomicU64, Ordering}, time::SystemTime};
+
+static COUNTER : AtomicU64 = AtomicU64::new(0);
+
+/// Formats 128 bits like a uuid, so the ids can be used as is by other tools(icalendar, taskwarrior)
+fn uuid_like(a : u64, b : u64) -> String {
+    // Mark it as a version 4, variant 1 uuid
+    let a = (a & 0xffff_ffff_ffff_0fff) | 0x4000;
+    let b = (b & 0x3fff_ffff_ffff_ffff) | 0x8000_0000_0000_0000;
+
+    format!("{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        a >> 32, (a >> 16) & 0xffff, a & 0xffff, b >> 48, b & 0xffff_ffff_ffff)
+}
+
+/**
+    64 bit FNV-1a of the parts, each followed by a `0xff` so `("ab", "c")` and `("a", "bc")` differ.
+    Unlike `DefaultHasher` it gives the same value on every platform and Rust version,
+    which derived ids need to agree between copies of a file.
+*/
+fn hash(parts : &[&[u8]]) -> u64 {
+    let mut hash : u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.iter().chain(&[0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// A new id that won't be repeated, made from the time, process and a counter
+pub fn new_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let half = |n : u8| hash(&[&[n], &nanos.to_le_bytes(), &process::id().to_le_bytes(), &count.to_le_bytes()]);
+
+    uuid_like(half(0), half(1))
+}
+
+/**
+    An id derived from `content`, used for tasks saved before tasks had ids
+    so copies of the same old file still agree on them.
+*/
+pub fn id_from(content : &[&str]) -> String {
+    // The marker of each half goes first so it mixes through the whole input
+    let half = |n : &str| {
+        let parts : Vec<&[u8]> = std::iter::once(n.as_bytes()).chain(content.iter().map(|c| c.as_bytes())).collect();
+        hash(&parts)
+    };
+
+    uuid_like(half("0"), half("1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derived_ids_are_fixed() {
+        assert_ne!(id_from(&["buy milk", "0"]), id_from(&["buy milk", "1"]));
+        assert_ne!(id_from(&["ab", "c"]), id_from(&["a", "bc"]));
+        assert_eq!(id_from(&["buy milk", "0"]), "5a493cac-013c-4b61-af73-d79b453feb56");
+    }
+}
@@ -0,0 +1,280 @@
+use serde_derive::{Serialize,Deserialize};
+use std::{fs, path::Path};
+use crate::{Task, archive::ArchivedTask, timestamp};
+
+/// How many entries are kept, older ones are dropped
+const MAX_ENTRIES : usize = 200;
+
+/**
+    A single task changed by a save, `None` before means it was added and `None` after
+    that it was removed. `at` is where the task was in the list, to put it back in place.
+    `archived` is the archive entry of a task removed to the archive or restored from it,
+    undoing and redoing move it between the list and the archive again.
+*/
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Change {
+    pub at : usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before : Option<Task>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after : Option<Task>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived : Option<ArchivedTask>,
+}
+impl Change {
+    /// Sets the task to `to`, finding it by id
+    fn apply(tasks : &mut Vec<Task>, at : usize, from : &Option<Task>, to : &Option<Task>) {
+        let id = match from.as_ref().or(to.as_ref()) {
+            Some(t) => &t.id,
+            None => return,
+        };
+
+        match (tasks.iter().position(|t| &t.id == id), to) {
+            (Some(i), Some(t)) => tasks[i] = t.clone(),
+            (Some(i), None) => { tasks.remove(i); },
+            (None, Some(t)) => tasks.insert(at.min(tasks.len()), t.clone()),
+            (None, None) => {},
+        }
+    }
+
+    /// What changed, like `edit "a": priority 0 -> 2`
+    pub fn describe(&self) -> String {
+        match (&self.before, &self.after) {
+            (None, Some(a)) if self.archived.is_some() => format!("restore \"{}\"", a.name),
+            (None, Some(a)) => format!("add \"{}\"", a.name),
+            (Some(b), None) => format!("remove \"{}\"", b.name),
+            (Some(b), Some(a)) => {
+                let fields = b.diff(a).iter()
+                    .map(|(field, before, after)| format!("{} {} -> {}", field, before, after))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("edit \"{}\": {}", b.name, fields)
+            }
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// The changes of one save
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Seconds since the epoch
+    pub time : i64,
+    pub summary : String,
+    pub changes : Vec<Change>,
+}
+impl Entry {
+    /**
+        Records the changes from `before` to `after`, `None` if nothing changed.
+        `archived` are the tasks moved to the archive by the save and `restored` the ones taken out of it.
+    */
+    pub fn between(before : &[Task], after : &[Task], archived : &[ArchivedTask], restored : &[ArchivedTask], summary : &str) -> Option<Entry> {
+        let entry = |list : &[ArchivedTask], id : &str| list.iter().find(|a| a.task.id == id).cloned();
+        let mut changes = Vec::new();
+
+        for (at, b) in before.iter().enumerate() {
+            match after.iter().find(|a| a.id == b.id) {
+                Some(a) if a != b => changes.push(Change { at, before : Some(b.clone()), after : Some(a.clone()), archived : None }),
+                Some(_) => {},
+                None => changes.push(Change { at, before : Some(b.clone()), after : None, archived : entry(archived, &b.id) }),
+            }
+        }
+        for (at, a) in after.iter().enumerate() {
+            if !before.iter().any(|b| b.id == a.id) {
+                changes.push(Change { at, before : None, after : Some(a.clone()), archived : entry(restored, &a.id) });
+            }
+        }
+
+        if changes.is_empty() {
+            None
+        }
+        else {
+            Some(Entry {
+                time : timestamp::now(),
+                summary : String::from(summary),
+                changes,
+            })
+        }
+    }
+
+    pub fn undo(&self, tasks : &mut Vec<Task>) {
+        // Backwards, so removed tasks go back to their old places
+        for c in self.changes.iter().rev() {
+            Change::apply(tasks, c.at, &c.after, &c.before);
+        }
+    }
+
+    pub fn redo(&self, tasks : &mut Vec<Task>) {
+        for c in self.changes.iter() {
+            Change::apply(tasks, c.at, &c.before, &c.after);
+        }
+    }
+
+    /**
+        The archive entries undoing(or redoing) this entry puts back in the archive
+        and the ids of the tasks it takes out of it.
+    */
+    pub fn archive_moves(&self, undo : bool) -> (Vec<ArchivedTask>, Vec<String>) {
+        let mut archived = Vec::new();
+        let mut restored = Vec::new();
+
+        for c in self.changes.iter() {
+            let a = match &c.archived {
+                Some(a) => a,
+                None => continue,
+            };
+            // Redoing a removal or undoing a restore puts the task back in the archive
+            if c.after.is_none() != undo {
+                archived.push(a.clone());
+            }
+            else {
+                restored.push(a.task.id.clone());
+            }
+        }
+
+        (archived, restored)
+    }
+
+    /// `d/m/y h:m` of when the change was made
+    pub fn time_formatted(&self) -> String {
+        timestamp::format(self.time)
+    }
+}
+
+/**
+    Journal of the changes made to a tasks file, so they can be undone and redone.
+    It's kept next to the file, as `.tasks.toml.journal` for `tasks.toml`.
+*/
+#[derive(Default, Serialize, Deserialize)]
+pub struct Journal {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub done : Vec<Entry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undone : Vec<Entry>,
+    #[serde(skip)]
+    path : String,
+}
+impl Journal {
+    /// The journal of a tasks file, `.tasks.toml.journal` for `tasks.toml`
+    pub fn path_for(path : &str) -> String {
+        let p = Path::new(path);
+        let file = p.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+        p.with_file_name(format!(".{}.journal", file)).to_string_lossy().into_owned()
+    }
+
+    pub fn for_file(path : &str) -> Result<Journal, String> {
+        let path = Journal::path_for(path);
+
+        let mut journal : Journal = match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("couldn't load {}: {}", path, e))?,
+            Err(_) => Journal::default(),
+        };
+        journal.path = path;
+
+        Ok(journal)
+    }
+
+    /// Adds a new entry, which makes the undone ones impossible to redo
+    pub fn record(&mut self, entry : Entry) {
+        self.done.push(entry);
+        self.undone.clear();
+
+        if self.done.len() > MAX_ENTRIES {
+            let extra = self.done.len() - MAX_ENTRIES;
+            self.done.drain(..extra);
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let text = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(&self.path, text).map_err(|e| format!("couldn't save {}: {}", self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::Reason;
+
+    fn task(id : &str) -> Task {
+        Task {
+            id : String::from(id),
+            ..Task::new(id)
+        }
+    }
+
+    fn ids(tasks : &[Task]) -> Vec<&str> {
+        tasks.iter().map(|t| t.id.as_str()).collect()
+    }
+
+    #[test]
+    fn undo_and_redo_edits() {
+        let before = vec![task("a"), task("b")];
+        let mut after = before.clone();
+        after[1].priority = 2;
+        after.push(task("c"));
+        let entry = Entry::between(&before, &after, &[], &[], "").unwrap();
+
+        let mut tasks = after.clone();
+        entry.undo(&mut tasks);
+        assert!(tasks == before);
+        entry.redo(&mut tasks);
+        assert!(tasks == after);
+
+        assert!(Entry::between(&before, &before, &[], &[], "").is_none());
+    }
+
+    #[test]
+    fn removal_to_the_archive_moves_back() {
+        let before = vec![task("a"), task("b"), task("c")];
+        let after = vec![task("a"), task("c")];
+        let archived = vec![ArchivedTask::new(before[1].clone(), Reason::Removed)];
+        let entry = Entry::between(&before, &after, &archived, &[], "").unwrap();
+
+        let mut tasks = after.clone();
+        entry.undo(&mut tasks);
+        assert_eq!(ids(&tasks), ["a", "b", "c"]);
+        let (to_archive, from_archive) = entry.archive_moves(true);
+        assert!(to_archive.is_empty());
+        assert_eq!(from_archive, ["b"]);
+
+        entry.redo(&mut tasks);
+        assert_eq!(ids(&tasks), ["a", "c"]);
+        let (to_archive, from_archive) = entry.archive_moves(false);
+        assert_eq!(to_archive.len(), 1);
+        assert_eq!(to_archive[0].task.id, "b");
+        assert!(from_archive.is_empty());
+    }
+
+    #[test]
+    fn restore_goes_back_to_the_archive() {
+        let before = vec![task("a")];
+        let after = vec![task("a"), task("b")];
+        let restored = vec![ArchivedTask::new(task("b"), Reason::Done)];
+        let entry = Entry::between(&before, &after, &[], &restored, "").unwrap();
+        assert_eq!(entry.changes[0].describe(), "restore \"b\"");
+
+        let mut tasks = after.clone();
+        entry.undo(&mut tasks);
+        assert_eq!(ids(&tasks), ["a"]);
+        let (to_archive, from_archive) = entry.archive_moves(true);
+        assert!(to_archive[0].reason == Reason::Done);
+        assert!(from_archive.is_empty());
+
+        let (to_archive, from_archive) = entry.archive_moves(false);
+        assert!(to_archive.is_empty());
+        assert_eq!(from_archive, ["b"]);
+    }
+
+    #[test]
+    fn plain_adds_and_removals_leave_the_archive_alone() {
+        let before = vec![task("a")];
+        let after = vec![task("b")];
+        let entry = Entry::between(&before, &after, &[], &[], "").unwrap();
+
+        for undo in [true, false] {
+            let (to_archive, from_archive) = entry.archive_moves(undo);
+            assert!(to_archive.is_empty() && from_archive.is_empty());
+        }
+    }
+}
@@ -1,94 +1,495 @@
-use datetime::Month;
+use datetime::{LocalDateTime, LocalTime, Month};
 use serde_derive::{Serialize,Deserialize};
 pub use datetime::{LocalDate, convenience::Today, DatePiece};
-use std::{error::Error, fs,env};
-
-
-#[derive(Serialize,Deserialize)]
+use std::{fs, mem};
+
+pub mod agenda;
+pub mod archive;
+pub mod calendar;
+pub mod color;
+pub mod config;
+pub mod format;
+pub mod history;
+pub mod ics;
+pub mod id;
+pub mod journal;
+pub mod lock;
+pub mod markdown;
+pub mod merge;
+pub mod paths;
+pub mod spreadsheet;
+pub mod sqlite;
+pub mod stats;
+pub mod storage;
+pub mod taskwarrior;
+pub mod timestamp;
+pub mod todotxt;
+pub mod upgrade;
+pub mod validate;
+use archive::{Archive, ArchivedTask, Reason};
+pub use color::Color;
+pub use config::Config;
+pub use format::Format;
+use history::History;
+use journal::{Entry, Journal};
+use storage::{Backend, Storage};
+
+#[derive(Serialize,Deserialize,Default)]
 pub struct TasksManager {
+    #[serde(default)]
     pub colors : FormatParams,
+    /// Skipped when empty, toml can't have `tasks = []` after the `[colors]` table
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tasks : Vec<Task>,
+    /// The config file the colors were loaded from, `None` when they live in the tasks file
+    #[serde(skip)]
+    pub config_path : Option<String>,
+    /// The file the tasks were loaded from and will be saved to
+    #[serde(skip)]
+    pub path : String,
+    /// How the tasks are kept at `path`, a text file in some format or a database
+    #[serde(skip)]
+    pub backend : Backend,
+    /// Settings from the config file, like the git history and journal
+    #[serde(skip)]
+    pub config : Config,
+    /// Tasks to move to the archive on the next save
+    #[serde(skip)]
+    archived : Vec<ArchivedTask>,
+    /// Tasks taken out of the archive since the last save, so the journal can put them back
+    #[serde(skip)]
+    restored : Vec<ArchivedTask>,
+    /// Version of the storage when it was loaded(`None` if it was empty) and the tasks it had,
+    /// used to notice someone else changed it before saving and to merge with their changes
+    #[serde(skip)]
+    loaded : Option<u64>,
+    #[serde(skip)]
+    base : Vec<Task>,
 }
 impl TasksManager {
     pub fn default_path() -> String {
-        let home = env::var("HOME");
-        let other = ".";
-
-        let mut res = match home {
-            Ok(s) => {
-                if !s.is_empty() {
-                    let mut s = s;
-                    s.push_str("/.local/share");
-                    s
-                }
-                else {
-                    String::from(other)
+        paths::data_file(None, None, "toml").path
+    }
+
+    /**
+        Loads the tasks file, with the backend picked from its extension or a `sqlite:` in front.
+        A missing file gives an empty manager while a file that fails to parse
+        (like a bad color) is an error.
+    */
+    pub fn load(path : &str) -> Result<TasksManager, String> {
+        let (path, backend) = storage::locate(path, None)?;
+        TasksManager::load_as(&path, backend, &Config::default_path())
+    }
+
+    /**
+        Loads the tasks kept at `path` by `backend` and, if it exists, the config file at `config_path`.
+        The config file overrides any colors still kept in the tasks file(the old combined format).
+    */
+    pub fn load_as(path : &str, backend : Backend, config_path : &str) -> Result<TasksManager, String> {
+        let (mut tasks, version) = storage::open(path, backend).load()?;
+        tasks.path = String::from(path);
+        tasks.backend = backend;
+        tasks.loaded = version;
+        tasks.assign_ids();
+        tasks.base = tasks.tasks.clone();
+
+        tasks.config = Config::load(config_path)?.unwrap_or_default();
+        if let Some(colors) = &tasks.config.colors {
+            tasks.colors = colors.clone();
+            tasks.config_path = Some(String::from(config_path));
+        }
+
+        Ok(tasks)
+    }
+
+    /// Moves the colors out of the tasks file into a config file at `config_path`
+    pub fn split_config(&mut self, config_path : &str) -> Result<(), String> {
+        let mut config = Config::load(config_path)?.unwrap_or_default();
+        config.colors = Some(self.colors.clone());
+        config.save(config_path)?;
+        self.config_path = Some(String::from(config_path));
+
+        Ok(())
+    }
+
+    /**
+        Gives an id to tasks without one(from files saved before tasks had ids).
+        The id is made from the task name so every copy of such a file gets the same ids,
+        even if the other fields were edited in one of them.
+    */
+    fn assign_ids(&mut self) {
+        for i in 0..self.tasks.len() {
+            if self.tasks[i].id.is_empty() {
+                let mut n = 0;
+                let mut id = id::id_from(&[&self.tasks[i].name, &n.to_string()]);
+                while self.tasks.iter().any(|o| o.id == id) {
+                    n += 1;
+                    id = id::id_from(&[&self.tasks[i].name, &n.to_string()]);
                 }
-            },
-            Err(_) => String::from(other)
-        };
-        
-        res.push_str("/tasks.toml");
+                self.tasks[i].id = id;
+            }
+        }
+    }
 
-        res
+    /// Where the tasks are kept, see `storage`
+    pub fn storage(&self) -> Box<dyn Storage> {
+        storage::open(&self.path, self.backend)
     }
 
-    pub fn default() -> TasksManager {
-        TasksManager {
-            colors : FormatParams {
-                default: String::new(),
-                category: String::new(),
-                sub_category: Some(String::new()),
-                prio_1: String::new(),
-                prio_2: String::new(),
-                prio_3: String::new(),
-                done : String::new(),
-                show_days_forward : -1,
-            },
-            tasks: Vec::new(),
+    /// The archived tasks of the list
+    pub fn archive(&self) -> Result<Archive, String> {
+        Archive::open(self.storage())
+    }
+
+    /// Whether someone else wrote to the file since it was loaded
+    pub fn changed_on_disk(&self) -> bool {
+        self.storage().version() != self.loaded
+    }
+
+    /**
+        Reloads the file from disk and merges it with the changes made since it was loaded,
+        returning the conflicts(which keep our side).
+    */
+    pub fn merge_from_disk(&mut self) -> Result<Vec<merge::Conflict>, String> {
+        let (mut theirs, version) = self.storage().load()?;
+        theirs.assign_ids();
+
+        let merged = merge::merge(&self.base, &self.tasks, &theirs.tasks, merge::Side::Ours);
+
+        if self.config_path.is_none() {
+            self.colors = theirs.colors;
         }
+        self.base = theirs.tasks;
+        self.tasks = merged.tasks;
+        self.loaded = version;
+
+        Ok(merged.conflicts)
     }
 
-    pub fn load(path : &str) -> TasksManager {
-        let default = TasksManager::default_path();
-        let path = if path.ends_with(".toml") { path } else { &default };
-    
-        let file = match fs::read_to_string(path) {
-            Ok(v) => v,
-            Err(_) => String::new(),
+    /**
+        Saves back to where the tasks were loaded from, in the same format.
+        Fails without writing anything if the file was changed since it was loaded,
+        see `merge_from_disk` and `save_anyway`.
+    */
+    pub fn save(&mut self) -> Result<(), String> {
+        self.write(true, true)
+    }
+
+    /// Saves even if the file was changed since it was loaded, dropping those changes
+    pub fn save_anyway(&mut self) -> Result<(), String> {
+        self.write(false, true)
+    }
+
+    /// Undoes the last change in the journal and saves, returning what was undone
+    pub fn undo(&mut self) -> Result<Option<String>, String> {
+        let mut journal = Journal::for_file(&self.path)?;
+        let entry = match journal.done.pop() {
+            Some(e) => e,
+            None => return Ok(None),
         };
 
-        toml::from_str(&file).unwrap_or(TasksManager::default())
+        let (archived, restored) = entry.archive_moves(true);
+        entry.undo(&mut self.tasks);
+        self.archived.extend(archived);
+        self.write(true, false)?;
+        self.unarchive(&restored)?;
+        let summary = entry.summary.clone();
+        journal.undone.push(entry);
+        journal.save()?;
+
+        Ok(Some(summary))
+    }
+
+    /// Redoes the last undone change and saves, returning what was redone
+    pub fn redo(&mut self) -> Result<Option<String>, String> {
+        let mut journal = Journal::for_file(&self.path)?;
+        let entry = match journal.undone.pop() {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+
+        let (archived, restored) = entry.archive_moves(false);
+        entry.redo(&mut self.tasks);
+        self.archived.extend(archived);
+        self.write(true, false)?;
+        self.unarchive(&restored)?;
+        let summary = entry.summary.clone();
+        journal.done.push(entry);
+        journal.save()?;
+
+        Ok(Some(summary))
     }
 
-    pub fn save(&self, path : &str) -> Result<(), impl Error> {
-        let toml = toml::to_string(self).unwrap();
+    /// `record` adds the changes to the journal, undo and redo write the journal themselves
+    fn write(&mut self, check : bool, record : bool) -> Result<(), String> {
+        self.stamp_changes();
 
-        eprintln!("saving at {}", path);
+        eprintln!("saving at {}", self.path);
 
-        let _ = fs::File::create(path);
-        fs::write(path, toml)
+        let summary = self.describe_changes();
+        let expected = if check { Some(self.loaded) } else { None };
+        let version = self.storage().save(self, &self.archived, &summary, expected)?;
+        let archived = mem::take(&mut self.archived);
+        let restored = mem::take(&mut self.restored);
 
+        if self.config.git_history {
+            if let Err(e) = History::for_file(&self.path).commit(&summary) {
+                eprintln!("saved but couldn't commit to the history: {}", e);
+            }
+        }
+        if self.config.journal && record {
+            if let Some(entry) = Entry::between(&self.base, &self.tasks, &archived, &restored, &summary) {
+                let journaled = Journal::for_file(&self.path).and_then(|mut j| {
+                    j.record(entry);
+                    j.save()
+                });
+                if let Err(e) = journaled {
+                    eprintln!("saved but couldn't write the journal: {}", e);
+                }
+            }
+        }
+        if let Some(ics_file) = &self.config.ics_file {
+            if let Err(e) = fs::write(ics_file, ics::export(&self.tasks, self.config.ics_events)) {
+                eprintln!("saved but couldn't export to {}: {}", ics_file, e);
+            }
+        }
+
+        self.loaded = version;
+        self.base = self.tasks.clone();
+
+        Ok(())
+    }
+
+    /// Takes tasks back out of the archive once the list is saved with them, for undo and redo
+    fn unarchive(&self, ids : &[String]) -> Result<(), String> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut archive = self.archive()?;
+        archive.remove_ids(ids);
+        archive.save()
     }
 
+    /**
+        Moves a task from the archive back to the list, a done task comes back as not done.
+        Refuses a task whose id is already in the list, like after undoing its removal.
+        Save the tasks before the archive, so the task isn't lost if saving fails.
+    */
+    pub fn restore(&mut self, archive : &mut Archive, at : usize) -> Result<Task, String> {
+        let id = match archive.tasks.get(at) {
+            Some(a) => a.task.id.clone(),
+            None => return Err(String::from("invalid number")),
+        };
+        if let Some(t) = self.tasks.iter().find(|t| t.id == id) {
+            return Err(format!("{} is already in the tasks", t.formatted(true)));
+        }
+
+        let archived = archive.take(at).ok_or_else(|| String::from("invalid number"))?;
+        let mut task = archived.task.clone();
+        if archived.reason == Reason::Done {
+            task.done = false;
+        }
+        self.restored.push(archived);
+        self.add_task(task.clone());
+
+        Ok(task)
+    }
+
+    /// A summary of what changed since the tasks were loaded, like `Add "a", edit "b"`
+    pub fn describe_changes(&self) -> String {
+        let names = |tasks : Vec<&Task>| tasks.iter()
+            .map(|t| format!("\"{}\"", t.name.trim()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let added : Vec<&Task> = self.tasks.iter().filter(|t| !self.base.iter().any(|b| b.id == t.id)).collect();
+        let edited : Vec<&Task> = self.tasks.iter().filter(|t| self.base.iter().any(|b| b.id == t.id && b != *t)).collect();
+        let removed : Vec<&Task> = self.base.iter().filter(|b| !self.tasks.iter().any(|t| t.id == b.id)).collect();
+
+        let mut parts = Vec::new();
+        for (what, tasks) in [("Add", added), ("Edit", edited), ("Remove", removed)] {
+            if !tasks.is_empty() {
+                parts.push(format!("{} {}", what, names(tasks)));
+            }
+        }
+
+        if parts.is_empty() {
+            String::from("Save without task changes")
+        }
+        else {
+            parts.join("; ")
+        }
+    }
+
+    /// Removes a task, moving it to the archive on save
     pub fn remove_task(&mut self, task_at : usize) {
         if task_at < self.tasks.len() {
-            self.tasks.remove(task_at);
+            let task = self.tasks.remove(task_at);
+            self.archived.push(ArchivedTask::new(task, Reason::Removed));
         }
     } 
 
-    pub fn remove_done(&mut self) {
-        self.tasks = self.tasks.iter()
-            .filter(|t| !(t.done && t.days_remianing().unwrap_or(-1) < 0))
-            .map(|t| t.clone())
-            .collect();
+    /**
+        Moves tasks that were done more than `after_days` days ago to the archive on save,
+        returns how many were archived. Tasks done before completion times were tracked
+        go by their due date instead(or right away without one).
+    */
+    pub fn archive_done(&mut self, after_days : i64) -> usize {
+        // Tasks marked done since loading get their completion time first
+        self.stamp_changes();
+
+        let now = timestamp::now();
+        let old = |t : &Task| match (t.completed, t.days_remianing()) {
+            (Some(c), _) => (now - c) / (60 * 60 * 24) > after_days,
+            (None, Some(d)) => -(d as i64) > after_days,
+            (None, None) => true,
+        };
+
+        let (old, keep) : (Vec<Task>, Vec<Task>) = self.tasks.drain(..)
+            .partition(|t| t.done && old(t));
+        self.tasks = keep;
+
+        let count = old.len();
+        self.archived.extend(old.into_iter().map(|t| ArchivedTask::new(t, Reason::Done)));
+
+        count
     }
 
     pub fn add_task(&mut self, task : Task) {
+        let mut task = task;
+        let now = timestamp::now();
+        task.created = task.created.or(Some(now));
+        task.modified = Some(now);
+        if task.done {
+            task.completed = task.completed.or(Some(now));
+        }
+
         self.tasks.push(task);
     }
 
+    /**
+        Adds tasks read from another tool, skipping the ones whose id is already in the list.
+        Tasks without an id get one made from their name and category.
+        Returns how many were added and how many were skipped.
+    */
+    pub fn import(&mut self, tasks : Vec<Task>) -> (usize, usize) {
+        let mut added = 0;
+        let mut skipped = 0;
+
+        let mut seen = Vec::new();
+        for mut task in tasks {
+            // Made from the task so importing the same file again finds them
+            if task.id.is_empty() {
+                let key = (task.name.clone(), task.category.clone());
+                let n = seen.iter().filter(|s| **s == key).count();
+                task.id = id::id_from(&[&key.0, &key.1, &n.to_string()]);
+                seen.push(key);
+            }
+            if self.tasks.iter().any(|t| t.id == task.id) {
+                skipped += 1;
+                continue;
+            }
+            self.add_task(task);
+            added += 1;
+        }
+
+        (added, skipped)
+    }
+
+    /**
+        Updates the timestamps of the tasks changed since they were loaded,
+        so every way of editing a task keeps them right.
+    */
+    fn stamp_changes(&mut self) {
+        let now = timestamp::now();
+
+        for t in self.tasks.iter_mut() {
+            let was_done = match self.base.iter().find(|b| b.id == t.id) {
+                Some(b) if b == t => continue,
+                Some(b) => b.done,
+                // New tasks keep the completion time they came with(imported ones)
+                None => {
+                    t.created = t.created.or(Some(now));
+                    t.done
+                }
+            };
+
+            t.modified = Some(now);
+            if !t.done {
+                t.completed = None;
+            }
+            else if !was_done || t.completed.is_none() {
+                t.completed = Some(now);
+            }
+        }
+    }
+
     pub fn full_print_for_conky(&self) -> String {
+        self.print_for_conky_with_prefix("")
+    }
+
+    /**
+        Prints several lists together, each list uses its own colors.
+        `label` picks between a header line per list and prefixing every category with the list name.
+    */
+    pub fn merged_print_for_conky(lists : &[(String, TasksManager)], label : ListLabel) -> String {
+        let mut res = String::new();
+
+        for (name, list) in lists {
+            match label {
+                ListLabel::Header => {
+                    let s = list.full_print_for_conky();
+                    if !s.is_empty() {
+                        res.push_str(&format!("{}[{}]\n", list.colors.category.to_conky(), name));
+                        res.push_str(&s);
+                    }
+                }
+                ListLabel::Prefix => {
+                    res.push_str(&list.print_for_conky_with_prefix(name));
+                }
+            }
+        }
+
+        res
+    }
+
+    /// The tasks grouped by their due date instead of their category, see `agenda::Buckets`
+    pub fn agenda_print_for_conky(&self) -> String {
+        let groups = self.config.agenda.group(self.agenda_items(""));
+        agenda::conky(&groups, &self.colors.category)
+    }
+
+    /// The tasks for the agenda, `list` is put in front of their categories
+    pub fn agenda_items<'a>(&'a self, list : &'a str) -> Vec<agenda::Item<'a>> {
+        self.tasks.iter()
+            .map(|task| agenda::Item { task, colors : &self.colors, list })
+            .collect()
+    }
+
+    /**
+        Same as `merged_print_for_conky` for the agenda.
+        With `ListLabel::Prefix` the lists share one agenda, using the buckets of the first.
+    */
+    pub fn merged_agenda_print_for_conky(lists : &[(String, TasksManager)], label : ListLabel) -> String {
+        match label {
+            ListLabel::Header => lists.iter()
+                .map(|(name, list)| (name, list, list.agenda_print_for_conky()))
+                .filter(|(_, _, s)| !s.is_empty())
+                .map(|(name, list, s)| format!("{}[{}]\n{}", list.colors.category.to_conky(), name, s))
+                .collect(),
+            ListLabel::Prefix => match lists.first() {
+                Some((_, first)) => {
+                    let items = lists.iter().flat_map(|(name, list)| list.agenda_items(name)).collect();
+                    agenda::conky(&first.config.agenda.group(items), &first.colors.category)
+                }
+                None => String::new(),
+            },
+        }
+    }
+
+    /// Same as `full_print_for_conky`, with `prefix` put in front of every category
+    fn print_for_conky_with_prefix(&self, prefix : &str) -> String {
         let tasks = &self.tasks;
         let colors = &self.colors;
 
@@ -112,21 +513,24 @@ impl TasksManager {
 
             for task in tasks {
                 if task.category == cat {
-                    group.push(&task);
+                    group.push(task);
                 }
             }
             group.sort_by_key(|a| -a.days_remianing().unwrap_or(-1));
             group.reverse();
     
             // Begin to print the stuff
-            if !cat.is_empty() {
-                gs.push_str(&format!("${{{}}}{}:\n", colors.category, cat));
+            if !prefix.is_empty() && !cat.is_empty() {
+                gs.push_str(&format!("{}{}/{}:\n", colors.category.to_conky(), prefix, cat));
+            }
+            else if !prefix.is_empty() || !cat.is_empty() {
+                gs.push_str(&format!("{}{}{}:\n", colors.category.to_conky(), prefix, cat));
             }
 
             let mut inserted = false;
 
             for t in group {
-                let t = t.formatted_conky(&colors, true);
+                let t = t.formatted_conky(colors, true);
                 if !t.is_empty(){
                     inserted = true;
                     gs.push_str(&format!(" {}\n", t));
@@ -144,11 +548,9 @@ impl TasksManager {
 
     pub fn tasks_list(&self) -> String {
         let mut s = String::new();
-        let mut i = 0;
 
-        for t in &self.tasks {
+        for (i, t) in self.tasks.iter().enumerate() {
             s.push_str(&format!("{}({}) {}\n",i, t.category,t.formatted(true)));
-            i += 1;
         }
 
         s
@@ -167,7 +569,40 @@ impl TasksManager {
     }
 }
 
-#[derive(Clone,Serialize,Deserialize, Copy)]
+/**
+    Limits tasks by their timestamps, each range is (after, before) in seconds since the epoch.
+    A task without the timestamp doesn't match a range that is set.
+*/
+#[derive(Default)]
+pub struct TimeFilter {
+    pub created : (Option<i64>, Option<i64>),
+    pub modified : (Option<i64>, Option<i64>),
+    pub completed : (Option<i64>, Option<i64>),
+}
+impl TimeFilter {
+    pub fn matches(&self, task : &Task) -> bool {
+        let within = |time : Option<i64>, range : (Option<i64>, Option<i64>)| match (range, time) {
+            ((None, None), _) => true,
+            (_, None) => false,
+            ((after, before), Some(t)) => after.map(|a| t >= a).unwrap_or(true) && before.map(|b| t < b).unwrap_or(true),
+        };
+
+        within(task.created, self.created)
+            && within(task.modified, self.modified)
+            && within(task.completed, self.completed)
+    }
+}
+
+/// How lists are told apart when printed together
+#[derive(Clone, Copy, PartialEq)]
+pub enum ListLabel {
+    /// A `[name]` line above the tasks of each list
+    Header,
+    /// Every category is printed as `name/category`
+    Prefix,
+}
+
+#[derive(Clone,Serialize,Deserialize, Copy, PartialEq, Debug)]
 pub struct Date {
     pub year : i64,
     pub day : i64,
@@ -185,10 +620,44 @@ impl Date{
         }
     }
     pub fn to_localdate(&self) -> Option<LocalDate> {
-        match LocalDate::yd(self.year, self.day) {
-            Ok(d) => Some(d),
-            Err(_) => None
+        LocalDate::yd(self.year, self.day).ok()
+    }
+    /// The day a timestamp(seconds since the epoch) falls on
+    pub fn from_timestamp(time : i64) -> Date {
+        Date::from(LocalDateTime::at(time).date())
+    }
+    /// Midnight of the date, in seconds since the epoch
+    pub fn timestamp(&self) -> Option<i64> {
+        self.to_localdate().map(|d| LocalDateTime::new(d, LocalTime::midnight()).to_instant().seconds())
+    }
+    /// The date as `yyyy-mm-dd`
+    pub fn to_iso(&self) -> Option<String> {
+        self.to_localdate().map(|d| format!("{:04}-{:02}-{:02}", d.year(), d.month() as i32, d.day()))
+    }
+    /// Reads a `yyyy-mm-dd` date
+    pub fn from_iso(s : &str) -> Option<Date> {
+        let mut parts = s.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+        let (y, m, d) = (parts.next()??, parts.next()??, parts.next()??);
+        if s.len() != 10 {
+            return None;
+        }
+
+        Date::from_ymd(y, m, d)
+    }
+    /// Reads a `d/m/y` date, like the ones typed in the dialogs
+    pub fn from_dmy(s : &str) -> Option<Date> {
+        let mut parts = s.trim().splitn(3, '/').map(|p| p.trim().parse::<i64>().ok());
+        let (d, m, y) = (parts.next()??, parts.next()??, parts.next()??);
+
+        Date::from_ymd(y, m, d)
+    }
+    /// The date of a year, month and day, `None` if there is no such day
+    pub fn from_ymd(year : i64, month : i64, day : i64) -> Option<Date> {
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
         }
+
+        LocalDate::ymd(year, Date::month_from_int(month as i32), day as i8).ok().map(Date::from)
     }
     /**
         Gives a month based on a number between 1(January) to 12(December)
@@ -212,25 +681,54 @@ impl Date{
     }
 }
 
-#[derive(Clone,Serialize,Deserialize)]
+#[derive(Clone,Serialize,Deserialize, PartialEq)]
 pub struct Task {
+    /// Stable identity of the task, kept across edits(and between copies of the file)
+    #[serde(default)]
+    pub id : String,
     pub category : String,
     pub sub_category : String,
     pub priority : u8,
     pub name : String,
     pub done : bool,
+    /// When the task was added, last changed and marked done, in seconds since the epoch.
+    /// Kept up to date when saving, `None` for tasks from before they were tracked.
+    #[serde(default)]
+    pub created : Option<i64>,
+    #[serde(default)]
+    pub modified : Option<i64>,
+    #[serde(default)]
+    pub completed : Option<i64>,
     pub due : Option<Date>,
 }
 impl Task {
     // Creators
     pub fn new(name : &str) -> Task {
+        let now = timestamp::now();
+
         Task {
+            id : id::new_id(),
             category : String::new(),
             sub_category : String::new(),
             priority : 0,
             name : String::from(name),
             due : None,
-            done : false
+            done : false,
+            created : Some(now),
+            modified : Some(now),
+            completed : None,
+        }
+    }
+    /**
+        A task read from another tool or file, without an id or times until the
+        reader finds them(`TasksManager::import` and loading give it an id otherwise).
+    */
+    pub fn imported(name : &str) -> Task {
+        Task {
+            id : String::new(),
+            created : None,
+            modified : None,
+            ..Task::new(name)
         }
     }
     pub fn due(self, due : Option<Date>) -> Task {
@@ -264,13 +762,78 @@ impl Task {
         }
     }
 
+    /// The fields that differ from `other`, as (field, ours, theirs)
+    pub fn diff(&self, other : &Task) -> Vec<(&'static str, String, String)> {
+        let due = |d : &Option<Date>| match d.and_then(|d| d.to_localdate()) {
+            Some(d) => format!("{}/{}/{}", d.day(), d.month() as i32, d.year()),
+            None => String::from("none"),
+        };
+
+        let mut res = Vec::new();
+        if self.name != other.name {
+            res.push(("name", self.name.clone(), other.name.clone()));
+        }
+        if self.category != other.category {
+            res.push(("category", self.category.clone(), other.category.clone()));
+        }
+        if self.sub_category != other.sub_category {
+            res.push(("sub category", self.sub_category.clone(), other.sub_category.clone()));
+        }
+        if self.priority != other.priority {
+            res.push(("priority", self.priority.to_string(), other.priority.to_string()));
+        }
+        if self.due != other.due {
+            res.push(("due", due(&self.due), due(&other.due)));
+        }
+        if self.done != other.done {
+            res.push(("done", self.done.to_string(), other.done.to_string()));
+        }
+
+        res
+    }
+
+    /// When it was added and done, like `added 3 days ago, done today`
+    pub fn times_formatted(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(c) = self.created {
+            parts.push(format!("added {}", timestamp::ago(c)));
+        }
+        if let Some(c) = self.completed {
+            parts.push(format!("done {}", timestamp::ago(c)));
+        }
+
+        parts.join(", ")
+    }
+
+    /// Every field of the task, one per line
+    pub fn details(&self) -> String {
+        let time = |t : Option<i64>| t.map(timestamp::format).unwrap_or_else(|| String::from("unknown"));
+        let due = match self.due.and_then(|d| d.to_localdate()) {
+            Some(d) => format!("{}/{}/{}", d.day(), d.month() as i32, d.year()),
+            None => String::from("none"),
+        };
+
+        let mut s = format!("name: {}\n", self.name);
+        s.push_str(&format!("category: {}\n", self.category));
+        s.push_str(&format!("sub category: {}\n", self.sub_category));
+        s.push_str(&format!("priority: {}\n", self.priority));
+        s.push_str(&format!("due: {}\n", due));
+        s.push_str(&format!("done: {}\n", self.done));
+        s.push_str(&format!("created: {}\n", time(self.created)));
+        s.push_str(&format!("modified: {}\n", time(self.modified)));
+        if self.done {
+            s.push_str(&format!("completed: {}\n", time(self.completed)));
+        }
+        s.push_str(&format!("id: {}\n", self.id));
+
+        s
+    }
+
     pub fn days_remianing(&self) -> Option<i16> {
         let today = LocalDate::today();
 
-        let due = match self.due {
-            Some(due) => due.to_localdate().unwrap(),
-            None => {return None;}
-        };
+        // A due date that doesn't exist is left to `validate`
+        let due = self.due?.to_localdate()?;
 
         let due = due.yearday() + if due.year() == today.year() + 1 { 365 } else { 0 };
 
@@ -294,8 +857,7 @@ impl Task {
 
         // Until here : '- [x] Task ( sub_cat )'
 
-        if let Some(due) = self.due {
-            let due = due.to_localdate().unwrap();
+        if let Some(due) = self.due.and_then(|d| d.to_localdate()) {
             let month = due.month();
             
 
@@ -312,11 +874,9 @@ impl Task {
         s
     }
 
-    pub fn formatted_conky(&self, colors : &FormatParams, sub : bool) -> String {
-        // Category:
-        //      - [x] Task (sub_cat) - due in X days for d/m
-        
-        let c = if self.done { 
+    /// The color the task is printed in, from its priority or being done
+    pub fn color<'a>(&self, colors : &'a FormatParams) -> &'a Color {
+        if self.done { 
             &colors.done
         } 
         else { 
@@ -326,28 +886,32 @@ impl Task {
                 3 => &colors.prio_3,
                 _ => &colors.default
             }
-        };
+        }
+    }
+
+    pub fn formatted_conky(&self, colors : &FormatParams, sub : bool) -> String {
+        // Category:
+        //      - [x] Task (sub_cat) - due in X days for d/m
+        
+        let c = self.color(colors);
 
         let days = self.days_remianing().unwrap_or(0);
         if (days > colors.show_days_forward && colors.show_days_forward > 0) || days < 0 {
             return String::new();
         }
 
-        let mut s = String::from(&format!("${{{}}}- ", c));
-        //                                       ^^^^^^ if you do {{}} it treats as a written explicitly {}(so it doesnt replace it)
+        let mut s = format!("{}- ", c.to_conky());
         
         s.push_str(&self.name);
 
         if !self.sub_category.is_empty() && sub {
             let co = if let Some(col) = &colors.sub_category { col } else { &colors.default };
-            s.push_str(&format!(" (${{{}}}{}${{{}}})",co, self.sub_category.trim(),c));
+            s.push_str(&format!(" ({}{}{})",co.to_conky(), self.sub_category.trim(),c.to_conky()));
         }
 
         // Until here : '- [x] Task ( sub_cat )'
 
-        if let Some(due) = self.due {
-            let due = due.to_localdate().unwrap();
-
+        if let Some(due) = self.due.and_then(|d| d.to_localdate()) {
             let month = due.month();
             
             if days == 0 {
@@ -364,12 +928,26 @@ impl Task {
 #[derive(Clone,Serialize,Deserialize)]
 
 pub struct FormatParams {
-    pub default : String,
-    pub category : String,
-    pub done : String,
-    pub prio_1 : String,
-    pub prio_2 : String,
-    pub prio_3 : String,
+    pub default : Color,
+    pub category : Color,
+    pub done : Color,
+    pub prio_1 : Color,
+    pub prio_2 : Color,
+    pub prio_3 : Color,
     pub show_days_forward : i16,
-    pub sub_category : Option<String>,
+    pub sub_category : Option<Color>,
+}
+impl Default for FormatParams {
+    fn default() -> FormatParams {
+        FormatParams {
+            default: Color::Default,
+            category: Color::Default,
+            sub_category: Some(Color::Default),
+            prio_1: Color::Default,
+            prio_2: Color::Default,
+            prio_3: Color::Default,
+            done : Color::Default,
+            show_days_forward : -1,
+        }
+    }
 }
\ No newline at end of file
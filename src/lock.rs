@@ -0,0 +1,55 @@
+use fs2::FileExt;
+use std::{collections::hash_map::DefaultHasher, fs::{File, OpenOptions}, hash::{Hash, Hasher}};
+
+/**
+    An advisory lock on a tasks file, released when dropped.
+
+    Conky, the cli and the gui can all touch the same file, so reads take a
+    shared lock and writes an exclusive one. The lock is on the tasks file itself
+    so no extra files are left around.
+*/
+pub struct FileLock {
+    file : File,
+}
+impl FileLock {
+    /// Waits for a shared lock, `None` if the file doesn't exist yet
+    pub fn shared(path : &str) -> Result<Option<FileLock>, String> {
+        let file = match File::open(path) {
+            Ok(f) => f,
+            Err(_) => return Ok(None),
+        };
+        file.lock_shared().map_err(|e| format!("couldn't lock {}: {}", path, e))?;
+
+        Ok(Some(FileLock { file }))
+    }
+
+    /// Waits for an exclusive lock, creating the file if needed
+    pub fn exclusive(path : &str) -> Result<FileLock, String> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("couldn't open {}: {}", path, e))?;
+        file.lock_exclusive().map_err(|e| format!("couldn't lock {}: {}", path, e))?;
+
+        Ok(FileLock { file })
+    }
+}
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/**
+    Hash of a file's content, used to notice it changed since it was loaded.
+    A missing file and an empty one (like one just created by `FileLock::exclusive`) are the same.
+*/
+pub fn content_hash(content : Option<&str>) -> Option<u64> {
+    let content = content.filter(|c| !c.is_empty())?;
+
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    Some(hasher.finish())
+}
@@ -1,34 +1,263 @@
-use clap::{Arg, SubCommand};
+use clap::{Arg, ArgGroup, SubCommand};
 use cool_organizer::*;
-use datetime::{DatePiece, LocalDate, Month};
-use std::io::{Write, stdin, stdout};
+use datetime::DatePiece;
+use std::io::{IsTerminal, stdout};
 
-fn main() {
-    let default_path = TasksManager::default_path();
+mod prompt;
 
+fn main() {
     let matches = clap::App::new("cool organizer")
         .arg(Arg::with_name("file")
                 .short("f")
+                .help("tasks file, `sqlite:FILE` for a database, see the paths subcommand for the default")
                 .long("file")
-                .default_value(&default_path)
                 .takes_value(true)
                 .value_name("FILE")
             )
+        .arg(Arg::with_name("list")
+                .help("uses a named list from the config instead of the tasks file")
+                .short("l")
+                .long("list")
+                .takes_value(true)
+                .value_name("LIST")
+                .conflicts_with("file")
+            )
+        .arg(Arg::with_name("format")
+                .help("format of the tasks file, detected from the extension by default")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&Format::NAMES)
+                .value_name("FORMAT")
+            )
+        .arg(Arg::with_name("config")
+                .help("config file, used for the colors when it exists")
+                .long("config")
+                .takes_value(true)
+                .value_name("CONFIG")
+            )
         .arg(Arg::with_name("conky")
                 .help("prints normal output for conky")
                 .short("c")
                 .long("conky")
                 .takes_value(false)    
             )
-        .arg(Arg::with_name("remove_done")
-                .help("removes all past tasks that are done")
+        .arg(Arg::with_name("agenda")
+                .help("groups the conky output by due date instead of category")
+                .long("agenda")
+            )
+        .arg(Arg::with_name("merge")
+                .help("prints several lists together, comma separated or `all`")
+                .long("merge")
+                .takes_value(true)
+                .value_name("LISTS")
+            )
+        .arg(Arg::with_name("merge_style")
+                .help("how merged lists are labeled")
+                .long("merge_style")
+                .takes_value(true)
+                .possible_values(&["header", "prefix"])
+                .default_value("header")
+            )
+        .arg(Arg::with_name("archive_done")
+                .help("archives tasks done more than `archive_after_days` days ago")
                 .short("r")
-                .long("remove_done")
+                .long("archive_done")
+                .alias("remove_done")
                 .takes_value(false)
             )
         .subcommand(SubCommand::with_name("create_example")
                 .about("creates a default config")
             )
+        .subcommand(SubCommand::with_name("paths")
+                .about("prints the files in use and why they were chosen")
+            )
+        .subcommand(SubCommand::with_name("list")
+                .about("shows the named lists")
+                .subcommand(SubCommand::with_name("new")
+                        .about("registers a new list")
+                        .arg(Arg::with_name("name").required(true))
+                        .arg(Arg::with_name("path").help("defaults to $XDG_DATA_HOME/cool_organizer/<name>.toml"))
+                    )
+                .subcommand(SubCommand::with_name("default")
+                        .about("sets the list used by default, no name goes back to the tasks file")
+                        .arg(Arg::with_name("name"))
+                    )
+            )
+        .subcommand(SubCommand::with_name("split_config")
+                .about("moves the colors from the tasks file into the config file")
+            )
+        .subcommand(SubCommand::with_name("merge")
+                .about("merges two diverged copies of a tasks file, saving the result in place of OURS")
+                .arg(Arg::with_name("base").required(true).help("the common ancestor of both copies"))
+                .arg(Arg::with_name("ours").required(true))
+                .arg(Arg::with_name("theirs").required(true))
+                .arg(Arg::with_name("output")
+                        .help("where to save the result instead of OURS")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                    )
+                .arg(Arg::with_name("prefer")
+                        .help("which side wins conflicting changes")
+                        .long("prefer")
+                        .takes_value(true)
+                        .possible_values(&["ours", "theirs"])
+                        .default_value("ours")
+                    )
+            )
+        .subcommand(SubCommand::with_name("history")
+                .about("shows the git history of the tasks file(see `git_history` in the config) or the saves of a sqlite list")
+                .arg(Arg::with_name("count")
+                        .short("n")
+                        .takes_value(true)
+                        .default_value("10")
+                    )
+            )
+        .subcommand(SubCommand::with_name("undo")
+                .about("undoes the last change from the journal")
+                .arg(Arg::with_name("git")
+                        .help("reverts a change from the git history instead")
+                        .long("git")
+                        .takes_value(true)
+                        .min_values(0)
+                        .value_name("REV")
+                    )
+            )
+        .subcommand(SubCommand::with_name("migrate")
+                .about("converts the tasks file into a sqlite database next to it, keeping the old file as .bak")
+            )
+        .subcommand(SubCommand::with_name("upgrade")
+                .about("saves a toml tasks file from an older version in the current format, older files are upgraded when loaded anyway")
+                .arg(Arg::with_name("dry_run")
+                        .help("only shows what would change")
+                        .long("dry_run")
+                    )
+            )
+        .subcommand(SubCommand::with_name("check")
+                .about("lists tasks with problems, like empty names, line breaks in a category or dates that don't exist")
+                .arg(Arg::with_name("fix")
+                        .help("repairs what can be repaired and saves")
+                        .long("fix")
+                    )
+            )
+        .subcommand(SubCommand::with_name("redo")
+                .about("redoes the last undone change")
+            )
+        .subcommand(SubCommand::with_name("log")
+                .about("shows the journal of changes")
+                .arg(Arg::with_name("count")
+                        .short("n")
+                        .takes_value(true)
+                        .default_value("10")
+                    )
+            )
+        .subcommand(SubCommand::with_name("archive")
+                .about("shows the archived tasks")
+                .subcommand(SubCommand::with_name("list")
+                        .about("lists the archived tasks")
+                    )
+                .subcommand(SubCommand::with_name("search")
+                        .about("finds archived tasks by name or category")
+                        .arg(Arg::with_name("query").required(true))
+                    )
+                .subcommand(SubCommand::with_name("restore")
+                        .about("moves an archived task back to the tasks")
+                        .arg(Arg::with_name("number").required(true).help("the number shown by `archive list`"))
+                    )
+            )
+        .subcommand(SubCommand::with_name("show")
+                .about("lists the tasks with when they were added and done, or every detail of one task")
+                .arg(Arg::with_name("number"))
+                .arg(date_arg("created_after", "only tasks added on or after the date"))
+                .arg(date_arg("created_before", "only tasks added before the date"))
+                .arg(date_arg("modified_after", "only tasks changed on or after the date"))
+                .arg(date_arg("modified_before", "only tasks changed before the date"))
+                .arg(date_arg("completed_after", "only tasks done on or after the date"))
+                .arg(date_arg("completed_before", "only tasks done before the date"))
+            )
+        .subcommand(SubCommand::with_name("stats")
+                .about("prints statistics about completed and overdue tasks")
+                .arg(Arg::with_name("days")
+                        .help("how many days of completed tasks to count")
+                        .long("days")
+                        .takes_value(true)
+                        .default_value("14")
+                    )
+                .arg(Arg::with_name("weeks")
+                        .help("how many weeks of completed tasks to count")
+                        .long("weeks")
+                        .takes_value(true)
+                        .default_value("8")
+                    )
+                .arg(Arg::with_name("category")
+                        .help("adds a burndown of the category")
+                        .long("category")
+                        .takes_value(true)
+                    )
+                .arg(date_arg("from", "first day of the burndown, two weeks ago by default"))
+                .arg(date_arg("to", "last day of the burndown, today by default"))
+                .arg(Arg::with_name("json")
+                        .help("prints json instead of text")
+                        .long("json")
+                    )
+            )
+        .subcommand(SubCommand::with_name("agenda")
+                .about("prints the tasks grouped by due date")
+                .arg(Arg::with_name("no_color")
+                        .help("prints without colors, also the default when NO_COLOR is set or not printing to a terminal")
+                        .long("no_color")
+                    )
+            )
+        .subcommand(SubCommand::with_name("calendar")
+                .about("prints a month or week of due tasks as a grid")
+                .arg(Arg::with_name("month")
+                        .help("month to show as m/y or an offset like +1, the current one by default")
+                        .long("month")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .min_values(0)
+                        .value_name("MONTH")
+                    )
+                .arg(Arg::with_name("week")
+                        .help("shows a week instead, as a d/m/y date in it or an offset like +1")
+                        .long("week")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .min_values(0)
+                        .value_name("WEEK")
+                        .conflicts_with("month")
+                    )
+                .arg(Arg::with_name("no_color")
+                        .help("prints without colors, also the default when NO_COLOR is set or not printing to a terminal")
+                        .long("no_color")
+                    )
+            )
+        .subcommand(SubCommand::with_name("export")
+                .about("writes the tasks in another format, `-` for stdout")
+                .arg(file_arg("ics", "iCalendar file with a VTODO per task"))
+                .arg(file_arg("todotxt", "todo.txt file"))
+                .arg(file_arg("markdown", "Markdown checklist, a heading per category"))
+                .arg(file_arg("csv", "csv file with the columns from the [csv] table of the config"))
+                .arg(file_arg("taskwarrior", "json for Taskwarrior's `task import`"))
+                .group(ArgGroup::with_name("to").args(&EXCHANGE_FORMATS).required(true))
+                .arg(Arg::with_name("events")
+                        .help("also adds a VEVENT for every task with a due date to the iCalendar file")
+                        .long("events")
+                    )
+            )
+        .subcommand(SubCommand::with_name("import")
+                .about("adds the tasks from a file of another format, skipping the ones already in the list")
+                .arg(file_arg("ics", "iCalendar file, its VTODO items are imported"))
+                .arg(file_arg("todotxt", "todo.txt file"))
+                .arg(file_arg("markdown", "Markdown file, headings become categories and checklist items tasks"))
+                .arg(file_arg("csv", "csv file with the columns from the [csv] table of the config"))
+                .arg(file_arg("taskwarrior", "json from Taskwarrior's `task export`"))
+                .group(ArgGroup::with_name("from").args(&EXCHANGE_FORMATS).required(true))
+                .arg(Arg::with_name("skip_invalid")
+                        .help("imports the valid csv rows even if some can't be read")
+                        .long("skip_invalid")
+                    )
+            )
         .subcommand(SubCommand::with_name("add")
                 .about("adds a new task")
             )
@@ -41,20 +270,277 @@ fn main() {
     .get_matches()
     ;
 
-    let path = matches.value_of("file").unwrap();
-    let mut tasks = TasksManager::load(&path);
+    let config_file = paths::config_file(matches.value_of("config"));
+    let config_path = config_file.path.as_str();
+    let mut config = Config::load(config_path).unwrap_or_else(|e| exit_with(e)).unwrap_or_default();
+
+    let data_file = match (matches.value_of("file"), matches.value_of("list")) {
+        (None, Some(name)) => config.list_file(name, "given with --list"),
+        (Some(f), _) => Ok(paths::data_file(Some(f), None, config.extension())),
+        (None, None) => config.default_list_file().map(|l| paths::data_file(None, l, config.extension())),
+    }.unwrap_or_else(|e| exit_with(e));
+    let path = storage::file_path(&data_file.path);
+
+    match matches.subcommand() {
+        ("paths", _) => {
+            for (name, file) in [("tasks", &data_file), ("config", &config_file)].iter() {
+                println!("{} file: {}{}", name, file.path, if file.exists() { "" } else { " (missing)" });
+                println!("    {}", file.reason);
+            }
+            if let Some(old) = paths::old_data_file() {
+                println!("{} is where older versions kept the tasks, it isn't used anymore", old);
+                println!("    if it's yours, move it to {}", paths::data_file(None, None, "toml").path);
+            }
+            return;
+        }
+        ("list", Some(args)) => {
+            match args.subcommand() {
+                ("new", Some(args)) => {
+                    let name = args.value_of("name").unwrap();
+                    let list = config.add_list(name, args.value_of("path")).unwrap_or_else(|e| exit_with(e));
+                    println!("list '{}' kept at {}", name, list);
+                }
+                ("default", Some(args)) => {
+                    match args.value_of("name") {
+                        Some(name) => {
+                            config.list_file(name, "").unwrap_or_else(|e| exit_with(e));
+                            config.default_list = Some(String::from(name));
+                        }
+                        None => config.default_list = None,
+                    }
+                }
+                _ => {
+                    for (name, list) in config.lists.iter() {
+                        let default = config.default_list.as_deref() == Some(name.as_str());
+                        println!("{} {} - {}", if default { "*" } else { " " }, name, list);
+                    }
+                    return;
+                }
+            }
+            config.save(config_path).unwrap_or_else(|e| exit_with(e));
+            return;
+        }
+        ("history", Some(args)) => {
+            let count = args.value_of("count").unwrap().parse().unwrap_or(10);
+            let git = history::History::for_file(path);
+
+            // Databases keep the summaries of their saves, git is still used if it was turned on
+            match storage::Backend::detect(&data_file.path, None) {
+                Ok(storage::Backend::Sqlite) if !git.exists() => {
+                    let saves = sqlite::Sqlite::new(path).history(count).unwrap_or_else(|e| exit_with(e));
+                    for (time, summary) in saves {
+                        println!("{} {}", timestamp::format(time), summary);
+                    }
+                }
+                _ => print!("{}", git.log(count).unwrap_or_else(|e| exit_with(e))),
+            }
+            return;
+        }
+        ("undo", Some(args)) if args.is_present("git") => {
+            let summary = history::History::for_file(path).undo(args.value_of("git").unwrap_or("HEAD"))
+                .unwrap_or_else(|e| exit_with(e));
+            println!("reverted: {}", summary);
+            return;
+        }
+        ("log", Some(args)) => {
+            let count = args.value_of("count").unwrap().parse().unwrap_or(10);
+            let journal = journal::Journal::for_file(path).unwrap_or_else(|e| exit_with(e));
+
+            for entry in journal.undone.iter().rev().take(count) {
+                println!("(undone) {} {}", entry.time_formatted(), entry.summary);
+            }
+            if !journal.undone.is_empty() {
+                println!();
+            }
+            for entry in journal.done.iter().rev().take(count) {
+                println!("{} {}", entry.time_formatted(), entry.summary);
+                for c in entry.changes.iter() {
+                    println!("    {}", c.describe());
+                }
+            }
+            return;
+        }
+        ("merge", Some(args)) => {
+            let load = |name| {
+                let (path, backend) = storage::locate(args.value_of(name).unwrap(), None)?;
+                TasksManager::load_as(&path, backend, config_path)
+            };
+            let base = load("base").unwrap_or_else(|e| exit_with(e));
+            let mut ours = load("ours").unwrap_or_else(|e| exit_with(e));
+            let theirs = load("theirs").unwrap_or_else(|e| exit_with(e));
+            let prefer = match args.value_of("prefer") {
+                Some("theirs") => merge::Side::Theirs,
+                _ => merge::Side::Ours,
+            };
+
+            let merged = merge::merge(&base.tasks, &ours.tasks, &theirs.tasks, prefer);
+            print_conflicts(&merged.conflicts, if prefer == merge::Side::Ours { "kept ours" } else { "kept theirs" });
+
+            ours.tasks = merged.tasks;
+            if let Some(output) = args.value_of("output") {
+                let (path, backend) = storage::locate(output, None).unwrap_or_else(|e| exit_with(e));
+                ours.path = path;
+                ours.backend = backend;
+            }
+            ours.save_anyway().unwrap_or_else(|e| exit_with(e));
+            return;
+        }
+        _ => {}
+    }
+
+    let backend = matches.value_of("format")
+        .map(Format::from_name)
+        .transpose()
+        .and_then(|f| storage::Backend::detect(&data_file.path, f));
+    let mut tasks = backend.and_then(|b| TasksManager::load_as(path, b, config_path))
+        .unwrap_or_else(|e| exit_with(e));
 
     let mut should_save = false;
 
     // Argument matches
-    if let Some(_a) = matches.args.get("remove_done") {
-        tasks.remove_done();
-        should_save = true;
+    if matches.is_present("archive_done") {
+        // On stderr, -r is usually run together with the conky output
+        let count = tasks.archive_done(tasks.config.archive_after_days);
+        if count > 0 {
+            eprintln!("archived {} done tasks", count);
+            should_save = true;
+        }
     }
 
     // sub command matches
-    let (command, _command_args) = matches.subcommand();
+    let (command, command_args) = matches.subcommand();
+    let invalid = tasks.tasks.iter().filter(|t| !validate::issues(t).is_empty()).count();
+    if invalid > 0 && command != "check" {
+        eprintln!("{} tasks in {} have problems, see `cool_organizer check`", invalid, path);
+    }
     match command {
+        "archive" => {
+            let mut archive = tasks.archive().unwrap_or_else(|e| exit_with(e));
+
+            match command_args.unwrap().subcommand() {
+                ("search", Some(args)) => {
+                    let found = archive.search(args.value_of("query").unwrap()).unwrap_or_else(|e| exit_with(e));
+                    for i in found {
+                        println!("{} {}", i, archive.tasks[i].formatted());
+                    }
+                }
+                ("restore", Some(args)) => {
+                    let restored = args.value_of("number").unwrap().parse()
+                        .map_err(|_| String::from("invalid number"))
+                        .and_then(|i| tasks.restore(&mut archive, i));
+
+                    match restored {
+                        Ok(task) => {
+                            println!("restored {}", task.formatted(true));
+                            // Save the tasks first, better to have it twice than to lose it
+                            tasks.save().unwrap_or_else(|e| exit_with(e));
+                            archive.save().unwrap_or_else(|e| exit_with(e));
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                _ => {
+                    for (i, a) in archive.tasks.iter().enumerate() {
+                        println!("{} {}", i, a.formatted());
+                    }
+                }
+            }
+        }
+        "migrate" => {
+            let db = storage::convert_to_sqlite(&tasks).unwrap_or_else(|e| exit_with(e));
+            println!("converted {} to {}, the old files are kept with a .bak extension", tasks.path, db);
+
+            let lists : Vec<String> = config.lists.iter()
+                .filter(|(_, p)| **p == data_file.path)
+                .map(|(name, _)| name.clone())
+                .collect();
+            for name in lists.iter() {
+                config.lists.insert(name.clone(), db.clone());
+                println!("list '{}' is now kept at {}", name, db);
+            }
+            if lists.is_empty() {
+                if data_file.path == format!("{}/cool_organizer/tasks.toml", paths::data_home()) {
+                    config.sqlite = true;
+                    println!("set `sqlite = true` in the config so it is used by default");
+                }
+                else {
+                    println!("use --file {} from now on", db);
+                }
+            }
+            config.save(config_path).unwrap_or_else(|e| exit_with(e));
+            return;
+        }
+        "upgrade" => {
+            if tasks.backend != storage::Backend::File(Format::Toml) {
+                println!("only toml files have a format version, {} is {}", path, match tasks.backend {
+                    storage::Backend::File(f) => f.to_string(),
+                    storage::Backend::Sqlite => String::from("a sqlite database, see `migrate`"),
+                });
+                return;
+            }
+
+            // Loading upgraded the tasks already, this is only to tell what it did
+            let text = std::fs::read_to_string(path).unwrap_or_default();
+            let upgrade = text.parse::<toml::Value>()
+                .map_err(|e| e.to_string())
+                .and_then(|mut value| upgrade::upgrade(&mut value))
+                .unwrap_or_else(|e| exit_with(format!("{}: {}", path, e)));
+
+            if text.is_empty() || !upgrade.needed() {
+                println!("{} is already at format version {}", path, upgrade::FORMAT_VERSION);
+                return;
+            }
+            println!("{} is at format version {}, upgrading to {}:", path, upgrade.from, upgrade::FORMAT_VERSION);
+            for change in upgrade.changes.iter() {
+                println!("    {}", change);
+            }
+            if upgrade.changes.is_empty() {
+                println!("    only adds format_version");
+            }
+
+            if command_args.unwrap().is_present("dry_run") {
+                println!("nothing was saved (dry run)");
+            }
+            else {
+                should_save = true;
+            }
+        }
+        "check" => {
+            let fix = command_args.unwrap().is_present("fix");
+            let mut left = 0;
+
+            for (i, task) in tasks.tasks.iter_mut().enumerate() {
+                let issues = validate::issues(task);
+                if issues.is_empty() {
+                    continue;
+                }
+                println!("{} {:?} in {:?}:", i, task.name, task.category);
+
+                if fix {
+                    for change in validate::fix(task) {
+                        println!("    fixed: {}", change);
+                    }
+                }
+                let issues = if fix { validate::issues(task) } else { issues };
+                for issue in issues.iter() {
+                    println!("    {}", issue.describe());
+                }
+                left += issues.len();
+            }
+
+            if fix && invalid > 0 {
+                should_save = true;
+            }
+            if left > 0 && !fix {
+                exit_with(format!("{} problems found, `check --fix` repairs what it can", left));
+            }
+            else if left > 0 {
+                eprintln!("{} problems left, edit or remove these tasks", left);
+            }
+            else if invalid == 0 {
+                println!("no problems found");
+            }
+        }
         "create_example" => {
             tasks.tasks.push(
                 Task::new("example")
@@ -65,35 +551,36 @@ fn main() {
     
             should_save = true;
         }
+        "split_config" => {
+            if tasks.config_path.is_some() {
+                println!("colors are already kept in {}", config_path);
+            }
+            else {
+                match tasks.split_config(config_path) {
+                    Ok(_) => {
+                        println!("colors moved to {}", config_path);
+                        should_save = true;
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        }
         "add" => {
-            // Do an "add task" dialog - use the stdout().flush() this time
-            println!("Add task dialog init...");
-    
-            // get the task name
-            let mut temp = String::new();
-    
-            print!("Name: ");
-            let _ = stdout().flush();
-            let _ = stdin().read_line(&mut temp);
-            let mut task = Task::new(temp.trim_end_matches('\n'));
-    
-            print!("Category: ");
-            temp.clear();
-            let _ = stdout().flush();
-            let _ = stdin().read_line(&mut temp);
-            task.category = String::from(temp.trim_end_matches('\n'));
-    
-            print!("Sub category: ");
-            temp.clear();
-            let _ = stdout().flush();
-            let _ = stdin().read_line(&mut temp);
-            task.sub_category = String::from(temp.trim_end_matches('\n'));
-    
-            print!("due(d/m/y): ");
-            temp.clear();
-            let _ = stdout().flush();
-            let _ = stdin().read_line(&mut temp);
-            task.due = parse_to_date(temp.trim_end_matches('\n'));
+            println!("Add task dialog init... (Ctrl-D to cancel)");
+
+            let mut task = Task::new(&prompt::ask("Name", None, name_answer).unwrap_or_else(aborted));
+            task.category = prompt::ask("Category", None, text_answer).unwrap_or_else(aborted);
+            task.sub_category = prompt::ask("Sub category", None, text_answer).unwrap_or_else(aborted);
+            task.priority = prompt::number("Priority(0-3)", Some("0"), 0, 3).unwrap_or_else(aborted) as u8;
+            task.due = prompt::ask("due(d/m/y)", Some("none"), due_answer).unwrap_or_else(aborted);
+
+            let issues = validate::issues(&task);
+            if !issues.is_empty() {
+                for issue in issues.iter() {
+                    println!("{}", issue.describe());
+                }
+                exit_with(String::from("the task wasn't added"));
+            }
     
             println!{"Task:"};
             println!("{}",task.formatted(true));
@@ -104,131 +591,196 @@ fn main() {
     
         }
         "edit" => {
-            should_save = true;
-
             println!("Tasks:");
             println!("{}",tasks.tasks_list());
-            print!("Task to edit(-1 to quit): ");
-            let _ = stdout().flush();
-            let mut task_number = String::with_capacity(4);
-            let _ = stdin().read_line(&mut task_number);
-            let t : i32 = task_number.trim().parse().unwrap_or(-1);
-            // got a task, what do we want to change?
-            // lets make sure we have a valid number tho
+            let last = tasks.tasks.len() as i64 - 1;
+            let t = prompt::number("Task to edit(-1 to quit)", None, -1, last).unwrap_or_else(aborted);
 
-            if t < -1 || t >= tasks.tasks.len() as i32 {
-                println!("invalid number");
-            }
-            else {
-                let mut t = tasks.tasks.get_mut(t as usize).unwrap();
+            if t >= 0 {
+                let t = tasks.tasks.get_mut(t as usize).unwrap();
+                let before = t.clone();
 
                 println!("Editing task:");
                 println!("{}",t.formatted(true));
                 println!("Choose a property to edit:");
                 println!("0 - name\n1 - category\n2 - sub category");
                 println!("3 - priority\n4 - due date\n5 - done status");
-                println!("(-1/s) - go back to task selection");
-                let _ = stdout().flush();
-
-                let mut property = String::with_capacity(3);
-                let _ = stdin().read_line(&mut property);
-                let mut buff = String::new();
-                match property.trim_end_matches('\n') {
-                    "0" => {
-                        println!("current: {}", &t.name);
-                        print!("new: ");
-                        stdout().flush().expect("couldnt flush output");
-                        stdin().read_line(&mut buff).expect("coudlnt get input");
-                        if buff.trim_matches('\n').is_empty() {
-                            println!("task name cannot be empty!");
-                        }
-                        else{
-                            t.name = String::from(buff.trim_matches('\n'));
-                        }
+                println!("-1 - quit");
+
+                // The current value is the default, so an empty answer keeps it
+                match prompt::number("Property", None, -1, 5).unwrap_or_else(aborted) {
+                    0 => t.name = prompt::ask("new name", Some(&before.name), name_answer).unwrap_or_else(aborted),
+                    1 => t.category = prompt::ask("new category(or none)", Some(&before.category), cleared_text_answer).unwrap_or_else(aborted),
+                    2 => t.sub_category = prompt::ask("new sub category(or none)", Some(&before.sub_category), cleared_text_answer).unwrap_or_else(aborted),
+                    3 => {
+                        let current = before.priority.to_string();
+                        t.priority = prompt::number("new priority(0-3)", Some(&current), 0, 3).unwrap_or_else(aborted) as u8;
                     }
-                    "1" => {
-                        println!("current: {}", &t.category);
-                        print!("new: ");
-                        stdout().flush().expect("couldnt flush output");
-                        stdin().read_line(&mut buff).expect("coudlnt get input");
-                        t.category = String::from(buff.trim_matches('\n'));
+                    4 => {
+                        let current = match t.due.and_then(|d| d.to_localdate()) {
+                            Some(d) => format!("{}/{}/{}",d.day(),d.month() as i16, d.year()),
+                            None => String::from("none"),
+                        };
+                        t.due = prompt::ask("new due(d/m/y or none)", Some(&current), due_answer).unwrap_or_else(aborted);
                     }
-                    "2" => {
-                        println!("current: {}", &t.sub_category);
-                        print!("new: ");
-                        stdout().flush().expect("couldnt flush output");
-                        stdin().read_line(&mut buff).expect("coudlnt get input");
-                        t.sub_category = String::from(buff.trim_matches('\n'));
+                    5 => t.done = prompt::confirm("done", t.done).unwrap_or_else(aborted),
+                    _ => {}
+                }
+
+                // Problems the task already had are left to `check`
+                let had : Vec<String> = validate::issues(&before).iter().map(|i| i.describe()).collect();
+                let new : Vec<String> = validate::issues(t).iter().map(|i| i.describe()).filter(|i| !had.contains(i)).collect();
+                if !new.is_empty() {
+                    for issue in new.iter() {
+                        println!("{}", issue);
                     }
-                    "3" => {
-                        println!("current: {}", &t.priority);
-                        print!("new(0-3): ");
-                        stdout().flush().expect("couldnt flush output");
-                        stdin().read_line(&mut buff).expect("coudlnt get input");
-                        t.priority = match buff.trim_matches('\n').parse() {
-                            Ok(p) => p,
-                            Err(e) => {
-                                println!("ERROR: {:?}",e);
-                                t.priority
-                            }
+                    println!("the task wasn't changed");
+                    *t = before;
+                }
+                else {
+                    should_save = *t != before;
+                }
+            }
+        }
+        "show" => {
+            let args = command_args.unwrap();
+            let date = |name| args.value_of(name).map(|d| match Date::from_dmy(d).and_then(|d| d.timestamp()) {
+                Some(t) => t,
+                None => exit_with(format!("invalid date for --{}: {}", name, d)),
+            });
+            let filter = TimeFilter {
+                created : (date("created_after"), date("created_before")),
+                modified : (date("modified_after"), date("modified_before")),
+                completed : (date("completed_after"), date("completed_before")),
+            };
+
+            match args.value_of("number").map(|n| n.parse::<usize>().ok().and_then(|n| tasks.tasks.get(n))) {
+                Some(Some(task)) => print!("{}", task.details()),
+                Some(None) => println!("invalid number"),
+                None => {
+                    for (i, t) in tasks.tasks.iter().enumerate().filter(|(_, t)| filter.matches(t)) {
+                        let times = t.times_formatted();
+                        if times.is_empty() {
+                            println!("{}({}) {}", i, t.category, t.formatted(true));
+                        }
+                        else {
+                            println!("{}({}) {} ({})", i, t.category, t.formatted(true), times);
                         }
                     }
-                    "4" => {
-                        let due = match &t.due {
-                            Some(d) => {
-                                let d = d.to_localdate().unwrap();
-                                format!("{}/{}/{}",d.day(),d.month() as i16, d.year())
-                            }
-                            None => {
-                                String::from("None")
-                            }
-                        };
+                }
+            }
+        }
+        "stats" => {
+            let args = command_args.unwrap();
+            let number = |name| match args.value_of(name).unwrap().parse::<i64>() {
+                Ok(n) if n > 0 => n,
+                _ => exit_with(format!("--{} has to be a positive number", name)),
+            };
+            let date = |name| args.value_of(name).map(|d| match Date::from_dmy(d).and_then(|d| d.timestamp()) {
+                Some(t) => t,
+                None => exit_with(format!("invalid date for --{}: {}", name, d)),
+            });
+
+            let now = timestamp::now();
+            let burndown = args.value_of("category").map(|c| (
+                c,
+                date("from").unwrap_or(now - 13 * 60 * 60 * 24),
+                date("to").unwrap_or(now),
+            ));
+            let archive = tasks.archive().unwrap_or_else(|e| exit_with(e));
+            let stats = stats::Stats::new(&tasks.tasks, &archive.tasks, number("days"), number("weeks"), burndown);
 
-                        println!("current: {}",due);
-                        print!("new(d/m/y): ");
-                        stdout().flush().expect("couldnt flush output");
-                        stdin().read_line(&mut buff).expect("coudlnt get input");
-                        t.due = parse_to_date(&buff.trim_matches('\n'));
+            if args.is_present("json") {
+                println!("{}", stats.to_json());
+            }
+            else {
+                print!("{}", stats.to_text());
+            }
+        }
+        "agenda" => {
+            let args = command_args.unwrap();
+            let groups = tasks.config.agenda.group(tasks.agenda_items(""));
+            println!("{}", agenda::text(&groups, &tasks.colors.category, use_ansi(args)).trim_end());
+        }
+        "calendar" => {
+            let args = command_args.unwrap();
+            let span = if args.is_present("week") {
+                calendar::Span::week(args.value_of("week"), Date::from_dmy)
+            }
+            else {
+                calendar::Span::month(args.value_of("month"))
+            };
+            let span = span.unwrap_or_else(|e| exit_with(e));
+            print!("{}", calendar::render(span, &tasks.tasks, &tasks.colors, use_ansi(args)));
+        }
+        "import" => {
+            let args = command_args.unwrap();
+            let (kind, file) = exchange_file(args);
+            let text = std::fs::read_to_string(file).unwrap_or_else(|e| exit_with(format!("couldn't read {}: {}", file, e)));
+            let imported = match kind {
+                "ics" => ics::parse(&text),
+                "markdown" => markdown::parse(&text),
+                "csv" => spreadsheet::parse(&text, &tasks.config.csv).map(|(imported, errors)| {
+                    for e in errors.iter() {
+                        eprintln!("{}: {}", file, e);
                     }
-                    "5" => {
-                        println!("current: {}", &t.done);
-                        print!("new(t/f): ");
-                        stdout().flush().expect("couldnt flush output");
-                        stdin().read_line(&mut buff).expect("coudlnt get input");
-                        t.done = buff.to_lowercase().starts_with('t');
+                    if !errors.is_empty() && !args.is_present("skip_invalid") {
+                        exit_with(String::from("nothing was imported, fix the rows or pass --skip_invalid"));
                     }
-                    _ => {
-                        println!("invalid option");
-                    },
+                    imported
+                }),
+                "taskwarrior" => taskwarrior::parse(&text),
+                _ => todotxt::parse(&text),
+            };
+            let imported = imported.unwrap_or_else(|e| exit_with(format!("{}: {}", file, e)));
 
-                }
+            let (added, skipped) = tasks.import(imported);
+            println!("imported {} tasks, skipped {} already in the list", added, skipped);
+            should_save = added > 0;
+        }
+        "export" => {
+            let args = command_args.unwrap();
+            let (kind, file) = exchange_file(args);
+            let text = match kind {
+                "ics" => ics::export(&tasks.tasks, args.is_present("events")),
+                "markdown" => markdown::write(&tasks.tasks),
+                "csv" => spreadsheet::write(&tasks.tasks, &tasks.config.csv).unwrap_or_else(|e| exit_with(e)),
+                "taskwarrior" => taskwarrior::write(&tasks.tasks),
+                _ => todotxt::write(&tasks.tasks),
+            };
+
+            if file == "-" {
+                print!("{}", text);
+            }
+            else {
+                std::fs::write(file, text).unwrap_or_else(|e| exit_with(format!("couldn't write {}: {}", file, e)));
+                println!("exported {} tasks to {}", tasks.tasks.len(), file);
+            }
+        }
+        "undo" => {
+            match tasks.undo().unwrap_or_else(|e| exit_with(e)) {
+                Some(summary) => println!("undone: {}", summary),
+                None => println!("nothing to undo"),
+            }
+        }
+        "redo" => {
+            match tasks.redo().unwrap_or_else(|e| exit_with(e)) {
+                Some(summary) => println!("redone: {}", summary),
+                None => println!("nothing to redo"),
             }
         }
         "remove" => {
             println!("Tasks:");
             println!("{}",tasks.tasks_list());
-            print!("Remove task: ");
-            stdout().flush().expect("couldn't flush to screen");
-
-            let mut input = String::new();
-            stdin().read_line(&mut input).expect("couldn't get input");
+            let last = tasks.tasks.len() as i64 - 1;
+            let t = prompt::number("Remove task(-1 to quit)", None, -1, last).unwrap_or_else(aborted);
 
-            let t : i32 = input.trim().parse().unwrap_or(-1);
-
-            if t < 0 || t >= tasks.tasks.len() as i32 {
-                println!("invalid operation");
-            }
-            else {
+            if t >= 0 {
                 println!("Are you sure you want to delete task:");
                 let task = tasks.tasks.get(t as usize).unwrap();
                 println!("({}) {}",task.category, task.formatted(true));
 
-                print!("[y/N]");
-                stdout().flush().expect("couldn't flush to screen");
-                input.clear();
-                stdin().read_line(&mut input).expect("couldn't get input");
-
-                if input.to_lowercase().starts_with('y') {
+                if prompt::confirm("Remove it?", false).unwrap_or_else(aborted) {
                     println!("ok... removing task...");
                     tasks.remove_task(t as usize);
                     should_save = true;
@@ -236,64 +788,165 @@ fn main() {
                 else {
                     println!("ok, no task was deleted");
                 }
-
             }
         }
         _ => {
-            println!("{}", tasks.full_print_for_conky().trim());
+            let by_date = matches.is_present("agenda") || config.agenda.conky;
+            match matches.value_of("merge") {
+                Some(names) => {
+                    let names : Vec<&str> = if names == "all" {
+                        config.lists.keys().map(|n| n.as_str()).collect()
+                    }
+                    else {
+                        names.split(',').map(|n| n.trim()).collect()
+                    };
+                    let lists = names.iter()
+                        .map(|n| {
+                            let file = config.list_file(n, "")?;
+                            let (path, backend) = storage::locate(&file.path, None)?;
+                            let list = TasksManager::load_as(&path, backend, config_path)?;
+                            Ok((String::from(*n), list))
+                        })
+                        .collect::<Result<Vec<_>, String>>()
+                        .unwrap_or_else(|e| exit_with(e));
+                    let label = match matches.value_of("merge_style") {
+                        Some("prefix") => ListLabel::Prefix,
+                        _ => ListLabel::Header,
+                    };
+
+                    if by_date {
+                        println!("{}", TasksManager::merged_agenda_print_for_conky(&lists, label).trim());
+                    }
+                    else {
+                        println!("{}", TasksManager::merged_print_for_conky(&lists, label).trim());
+                    }
+                }
+                None if by_date => println!("{}", tasks.agenda_print_for_conky().trim()),
+                None => println!("{}", tasks.full_print_for_conky().trim()),
+            }
         }
     }
 
     if should_save {
-        let _ = tasks.save(&path);
+        let mut overwrite = false;
+
+        if tasks.config.auto_archive {
+            tasks.archive_done(tasks.config.archive_after_days);
+        }
+
+        if tasks.changed_on_disk() {
+            println!("{} was changed since it was loaded", path);
+            let choice = prompt::ask("[m]erge your changes into it, [o]verwrite it or [a]bort", Some("a"), |answer| {
+                match answer.to_lowercase().as_str() {
+                    "m" | "merge" => Ok('m'),
+                    "o" | "overwrite" => Ok('o'),
+                    "a" | "abort" => Ok('a'),
+                    _ => Err(format!("'{}' isn't m, o or a", answer)),
+                }
+            }).unwrap_or_else(aborted);
+
+            match choice {
+                'm' => {
+                    let conflicts = tasks.merge_from_disk().unwrap_or_else(|e| exit_with(e));
+                    print_conflicts(&conflicts, "kept yours");
+                }
+                'o' => overwrite = true,
+                _ => exit_with(String::from("nothing was saved")),
+            }
+        }
+
+        let saved = if overwrite { tasks.save_anyway() } else { tasks.save() };
+        saved.unwrap_or_else(|e| exit_with(e));
     }
 }
 
-fn parse_to_date(s : &str) -> Option<Date> {
-    if s.is_empty() {
-        None
+/// A `--name d/m/y` argument
+fn date_arg(name : &'static str, help : &'static str) -> Arg<'static, 'static> {
+    Arg::with_name(name)
+        .long(name)
+        .help(help)
+        .takes_value(true)
+        .value_name("d/m/y")
+}
+
+/// The formats `import` and `export` take a file for
+const EXCHANGE_FORMATS : [&str; 5] = ["ics", "todotxt", "markdown", "csv", "taskwarrior"];
+
+fn file_arg(name : &'static str, help : &'static str) -> Arg<'static, 'static> {
+    Arg::with_name(name)
+        .long(name)
+        .help(help)
+        .takes_value(true)
+        .value_name("FILE")
+}
+
+/// Which of the `EXCHANGE_FORMATS` was given to `import` or `export`, and its file
+fn exchange_file<'a>(args : &'a clap::ArgMatches) -> (&'static str, &'a str) {
+    EXCHANGE_FORMATS.iter()
+        .find_map(|kind| args.value_of(kind).map(|file| (*kind, file)))
+        .unwrap()
+}
+
+fn print_conflicts(conflicts : &[merge::Conflict], resolution : &str) {
+    if conflicts.is_empty() {
+        return;
     }
-    else {
-        let s = s.split('/').collect::<Vec<_>>();
 
-        if s.len() == 3 {
-            let d = match s[0].parse::<i16>() {
-                Ok(d) => d,
-                Err(_) => { return None; }
-            };
-            let m = match s[1].parse::<i16>() {
-                Ok(m) => m,
-                Err(_) => { return None; }
-            };
-            let y = match s[2].parse::<i64>() {
-                Ok(y) => y,
-                Err(_) => { return None; }
-            };
+    println!("{} conflicts, {}:", conflicts.len(), resolution);
+    for c in conflicts {
+        println!("  {} ({}) - {}", c.name, c.id, c.field);
+        println!("      ours:   {}", c.ours);
+        println!("      theirs: {}", c.theirs);
+    }
+}
 
-            let m = match m {
-                1 => Month::January,
-                2 => Month::February,
-                3 => Month::March,
-                4 => Month::April,
-                5 => Month::May,
-                6 => Month::June,
-                7 => Month::July,
-                8 => Month::August,
-                9 => Month::September,
-                10 => Month::October,
-                11 => Month::November,
-                _ => Month::December,
-            };
+/// Ends a dialog that was cancelled, before anything is saved
+fn aborted<T>(_ : prompt::Aborted) -> T {
+    println!();
+    exit_with(String::from("aborted, nothing was saved"));
+}
 
-            let date = match LocalDate::ymd(y, m, d as i8) {
-                Ok(d) => d,
-                Err(_) => { return None; }
-            };
+/// A task name typed in a dialog, which can't be empty
+fn name_answer(answer : &str) -> Result<String, String> {
+    if answer.is_empty() {
+        return Err(String::from("the name can't be empty"));
+    }
+    text_answer(answer)
+}
 
-            Some(Date::from(date))
-        }
-        else {
-            None
-        }
+/// A category or other text typed in a dialog
+fn text_answer(answer : &str) -> Result<String, String> {
+    match validate::text(answer) {
+        Some(problem) => Err(format!("the text {}", problem)),
+        None => Ok(String::from(answer)),
     }
-}
\ No newline at end of file
+}
+
+/// Like `text_answer`, with `none` to clear a field whose current value is the default
+fn cleared_text_answer(answer : &str) -> Result<String, String> {
+    if answer.eq_ignore_ascii_case("none") {
+        Ok(String::new())
+    }
+    else {
+        text_answer(answer)
+    }
+}
+
+/// A due date typed in a dialog, nothing or `none` for no due date
+fn due_answer(answer : &str) -> Result<Option<Date>, String> {
+    match answer.to_lowercase().as_str() {
+        "" | "none" => Ok(None),
+        _ => Date::from_dmy(answer).map(Some).ok_or(format!("'{}' isn't a d/m/y date", answer)),
+    }
+}
+
+/// Colors for the terminal, unless `--no_color` or `$NO_COLOR` are given or the output isn't a terminal
+fn use_ansi(args : &clap::ArgMatches) -> bool {
+    !args.is_present("no_color") && std::env::var_os("NO_COLOR").is_none() && stdout().is_terminal()
+}
+
+/// Prints the error and quits without saving anything
+fn exit_with(e : String) -> ! {
+    eprintln!("{}", e);
+    std::process::exit(1);
+}
@@ -0,0 +1,233 @@
+use crate::{Date, Task};
+
+/// A date as `yyyy-mm-dd` or `d/m/y`, optionally after `due`, `due:` or `📅`
+fn date(s : &str) -> Option<Date> {
+    let s = s.trim();
+    let s = ["due:", "due ", "📅"].iter().find_map(|p| s.strip_prefix(p)).unwrap_or(s).trim();
+
+    Date::from_iso(s).or_else(|| Date::from_dmy(s))
+}
+
+/// Splits a trailing date, like `(due 2026-10-25)` or `- 25/10/2026`, off an item
+fn trailing_date(text : &str) -> (&str, Option<Date>) {
+    let text = text.trim_end();
+
+    if let Some(open) = text.strip_suffix(')').and_then(|t| t.rfind('(')) {
+        if let Some(due) = date(&text[open + 1..text.len() - 1]) {
+            return (text[..open].trim_end(), Some(due));
+        }
+    }
+
+    let (rest, last) = match text.rsplit_once(' ') {
+        Some(split) => split,
+        None => return (text, None),
+    };
+    match date(last) {
+        Some(due) => {
+            let rest = rest.trim_end();
+            let rest = ["due", "due:", "📅", "-"].iter()
+                .find_map(|p| rest.strip_suffix(p).filter(|r| r.is_empty() || r.ends_with(' ')))
+                .unwrap_or(rest);
+            (rest.trim_end(), Some(due))
+        }
+        None => (text, None),
+    }
+}
+
+/// The `key:value` pairs of a trailing `<!-- ... -->` comment, and the text before it
+fn trailing_comment(text : &str) -> (&str, Vec<(&str, &str)>) {
+    let trimmed = text.trim_end();
+    match trimmed.strip_suffix("-->").and_then(|t| t.rsplit_once("<!--")) {
+        Some((rest, comment)) => (rest, comment.split_whitespace().filter_map(|kv| kv.split_once(':')).collect()),
+        None => (text, Vec::new()),
+    }
+}
+
+/// A checklist item, `- [ ] text` or `- [x] text` with `*` or `+` bullets as well
+fn checklist_item(line : &str) -> Option<(bool, &str)> {
+    let line = line.trim_start();
+    let line = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ "))?;
+
+    match line.trim_start().get(..3)? {
+        "[ ]" => Some((false, &line.trim_start()[3..])),
+        "[x]" | "[X]" => Some((true, &line.trim_start()[3..])),
+        _ => None,
+    }
+}
+
+/**
+    Reads the checklist items of a Markdown file as tasks.
+    The least nested headings are the categories and any deeper heading is a sub category,
+    trailing dates are the due dates and the other lines are ignored.
+*/
+pub fn parse(text : &str) -> Result<Vec<Task>, String> {
+    let category_level = text.lines()
+        .filter_map(|l| heading(l).map(|(level, _)| level))
+        .min();
+
+    let mut tasks = Vec::new();
+    let mut category = String::new();
+    let mut sub_category = String::new();
+
+    for (n, line) in text.lines().enumerate() {
+        if let Some((level, title)) = heading(line) {
+            if Some(level) == category_level {
+                category = String::from(title);
+                sub_category.clear();
+            }
+            else {
+                sub_category = String::from(title);
+            }
+            continue;
+        }
+
+        let (done, item) = match checklist_item(line) {
+            Some(item) => item,
+            None => continue,
+        };
+
+        let mut task = Task::imported("");
+        task.done = done;
+        task.category = category.clone();
+        task.sub_category = sub_category.clone();
+
+        let (item, keys) = trailing_comment(item);
+        for (key, value) in keys {
+            let number = || value.parse::<i64>().map_err(|_| format!("line {}: invalid {} '{}'", n + 1, key, value));
+            match key {
+                "id" => task.id = String::from(value),
+                "priority" => task.priority = number()?.clamp(0, 3) as u8,
+                "created" => task.created = Some(number()?),
+                "completed" => task.completed = Some(number()?),
+                _ => {}
+            }
+        }
+
+        let (name, due) = trailing_date(item);
+        task.name = String::from(name.trim());
+        task.due = due;
+
+        tasks.push(task);
+    }
+
+    Ok(tasks)
+}
+
+/// The level and title of a `# heading` line, a bare `#` has an empty title
+fn heading(line : &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    match rest.strip_prefix(' ') {
+        _ if level == 0 => None,
+        Some(title) => Some((level, title.trim().trim_end_matches('#').trim_end())),
+        None if rest.trim().is_empty() => Some((level, "")),
+        None => None,
+    }
+}
+
+/// A heading line, a bare `#` for an empty title, with a blank line before it when needed
+fn push_heading(s : &mut String, level : usize, title : &str) {
+    if !s.is_empty() && !s.ends_with("\n\n") {
+        s.push('\n');
+    }
+    let marks = "#".repeat(level);
+    if title.is_empty() {
+        s.push_str(&format!("{}\n\n", marks));
+    }
+    else {
+        s.push_str(&format!("{} {}\n\n", marks, title));
+    }
+}
+
+/**
+    The tasks as a Markdown checklist in their order, with a `#` heading whenever the category changes
+    and a `##` whenever the sub category does. A bare `#` or `##` goes back to no category or sub category.
+    What Markdown can't show(id, priority and times) is kept in a comment at the end of each item,
+    so the file reads back to the same tasks.
+*/
+pub fn write(tasks : &[Task]) -> String {
+    let mut s = String::new();
+
+    // Without any `#` the `##` headings would be read as categories
+    if tasks.iter().all(|t| t.category.trim().is_empty()) && tasks.iter().any(|t| !t.sub_category.trim().is_empty()) {
+        push_heading(&mut s, 1, "");
+    }
+
+    let mut category = "";
+    let mut sub = "";
+    for task in tasks {
+        if task.category.trim() != category {
+            category = task.category.trim();
+            sub = "";
+            push_heading(&mut s, 1, category);
+        }
+        if task.sub_category.trim() != sub {
+            sub = task.sub_category.trim();
+            push_heading(&mut s, 2, sub);
+        }
+
+        s.push_str(&format!("- [{}] {}", if task.done { "x" } else { " " }, task.name.trim()));
+        if let Some(due) = task.due.and_then(|d| d.to_iso()) {
+            s.push_str(&format!(" (due {})", due));
+        }
+
+        let mut keys = vec![format!("id:{}", task.id)];
+        if task.priority > 0 {
+            keys.push(format!("priority:{}", task.priority));
+        }
+        if let Some(created) = task.created {
+            keys.push(format!("created:{}", created));
+        }
+        if let (true, Some(completed)) = (task.done, task.completed) {
+            keys.push(format!("completed:{}", completed));
+        }
+        s.push_str(&format!(" <!-- {} -->\n", keys.join(" ")));
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(name : &str, category : &str, sub_category : &str) -> Task {
+        Task {
+            category : String::from(category),
+            sub_category : String::from(sub_category),
+            priority : 2,
+            due : Date::from_iso("2026-10-25"),
+            ..Task::new(name)
+        }
+    }
+
+    /// Writes and reads back the tasks, which should give them back apart from `modified`
+    fn round_trip(tasks : &[Task]) -> bool {
+        let expected : Vec<Task> = tasks.iter().cloned().map(|t| Task { modified : None, ..t }).collect();
+        parse(&write(tasks)).unwrap() == expected
+    }
+
+    #[test]
+    fn round_trip_keeps_order_and_categories() {
+        let mut done = task("done", "work", "");
+        done.done = true;
+        let tasks = vec![
+            task("no category", "", ""),
+            task("sub only", "", "errands"),
+            task("a", "work", "meetings"),
+            done,
+            task("back to none", "", ""),
+            task("b", "work", "meetings"),
+            task("c", "home", "meetings"),
+        ];
+
+        assert!(round_trip(&tasks));
+    }
+
+    #[test]
+    fn sub_categories_alone_stay_sub_categories() {
+        let tasks = vec![task("a", "", "errands"), task("b", "", "")];
+
+        assert!(round_trip(&tasks));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::Task;
+
+/// A field both sides changed to different values
+pub struct Conflict {
+    pub id : String,
+    pub name : String,
+    pub field : &'static str,
+    pub ours : String,
+    pub theirs : String,
+}
+
+pub struct Merged {
+    pub tasks : Vec<Task>,
+    pub conflicts : Vec<Conflict>,
+}
+
+/// Which side wins a conflict
+#[derive(Clone, Copy, PartialEq)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// Text for a side of a conflict, `removed` when the task is gone on that side
+fn describe(task : Option<&Task>) -> String {
+    match task {
+        Some(t) => t.formatted(true),
+        None => String::from("removed"),
+    }
+}
+
+/// Both values of the one field `changed` differs from `ours` in, formatted like `Task::diff`
+fn values(ours : &Task, changed : &Task) -> (String, String) {
+    ours.diff(changed)
+        .pop()
+        .map(|(_, o, t)| (o, t))
+        .unwrap_or_default()
+}
+
+/**
+    Merges the tasks of a single id field by field. Whatever only one side changed
+    is taken from that side, the rest is a conflict won by `prefer`.
+*/
+fn merge_task(base : Option<&Task>, ours : &Task, theirs : &Task, prefer : Side, conflicts : &mut Vec<Conflict>) -> Task {
+    let mut merged = ours.clone();
+
+    macro_rules! field {
+        ($field:ident) => {
+            let b = base.map(|b| &b.$field);
+            if ours.$field != theirs.$field {
+                if b == Some(&ours.$field) {
+                    merged.$field = theirs.$field.clone();
+                }
+                else if b != Some(&theirs.$field) {
+                    let mut changed = ours.clone();
+                    changed.$field = theirs.$field.clone();
+                    let (ours_value, theirs_value) = values(ours, &changed);
+                    conflicts.push(Conflict {
+                        id : ours.id.clone(),
+                        name : ours.name.clone(),
+                        field : stringify!($field),
+                        ours : ours_value,
+                        theirs : theirs_value,
+                    });
+                    if prefer == Side::Theirs {
+                        merged.$field = theirs.$field.clone();
+                    }
+                }
+            }
+        };
+    }
+    field!(name);
+    field!(category);
+    field!(sub_category);
+    field!(priority);
+    field!(done);
+    field!(due);
+
+    merged
+}
+
+/**
+    Three way merge of task lists, matching tasks by their id.
+
+    Edits of different fields of the same task are both kept, a task removed on one side
+    stays removed unless the other side edited it, in which case it's a conflict.
+    The result follows the order of `ours` with tasks added in `theirs` at the end.
+*/
+pub fn merge(base : &[Task], ours : &[Task], theirs : &[Task], prefer : Side) -> Merged {
+    let find = |list : &[Task], id : &str| list.iter().find(|t| t.id == id).cloned();
+
+    let mut tasks = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for o in ours {
+        let b = find(base, &o.id);
+        match (b.as_ref(), find(theirs, &o.id)) {
+            (b, Some(t)) => tasks.push(merge_task(b, o, &t, prefer, &mut conflicts)),
+            // Removed by them, fine as long as we didn't change it
+            (Some(b), None) => {
+                if b != o {
+                    conflicts.push(Conflict {
+                        id : o.id.clone(),
+                        name : o.name.clone(),
+                        field : "task",
+                        ours : describe(Some(o)),
+                        theirs : describe(None),
+                    });
+                    if prefer == Side::Ours {
+                        tasks.push(o.clone());
+                    }
+                }
+            }
+            // Added by us
+            (None, None) => tasks.push(o.clone()),
+        }
+    }
+
+    for t in theirs {
+        if find(ours, &t.id).is_some() {
+            continue;
+        }
+        match find(base, &t.id) {
+            // Removed by us, fine as long as they didn't change it
+            Some(b) => {
+                if &b != t {
+                    conflicts.push(Conflict {
+                        id : t.id.clone(),
+                        name : t.name.clone(),
+                        field : "task",
+                        ours : describe(None),
+                        theirs : describe(Some(t)),
+                    });
+                    if prefer == Side::Theirs {
+                        tasks.push(t.clone());
+                    }
+                }
+            }
+            // Added by them
+            None => tasks.push(t.clone()),
+        }
+    }
+
+    Merged {
+        tasks,
+        conflicts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id : &str, name : &str) -> Task {
+        Task {
+            id : String::from(id),
+            ..Task::new(name)
+        }
+    }
+
+    fn ids(merged : &Merged) -> Vec<&str> {
+        merged.tasks.iter().map(|t| t.id.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_tasks_added_on_either_side() {
+        let base = vec![task("a", "a")];
+        let ours = vec![task("a", "a"), task("b", "b")];
+        let theirs = vec![task("a", "a"), task("c", "c")];
+
+        let merged = merge(&base, &ours, &theirs, Side::Ours);
+        assert_eq!(ids(&merged), ["a", "b", "c"]);
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn drops_tasks_removed_on_either_side() {
+        let base = vec![task("a", "a"), task("b", "b"), task("c", "c")];
+        let ours = vec![task("a", "a"), task("c", "c")];
+        let theirs = vec![task("a", "a"), task("b", "b")];
+
+        let merged = merge(&base, &ours, &theirs, Side::Ours);
+        assert_eq!(ids(&merged), ["a"]);
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn combines_edits_of_different_fields() {
+        let base = vec![task("a", "a")];
+        let mut ours = base.clone();
+        ours[0].priority = 2;
+        let mut theirs = base.clone();
+        theirs[0].category = String::from("home");
+
+        let merged = merge(&base, &ours, &theirs, Side::Ours);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.tasks[0].priority, 2);
+        assert_eq!(merged.tasks[0].category, "home");
+    }
+
+    #[test]
+    fn reports_conflicts_with_formatted_values() {
+        let base = vec![task("a", "a")];
+        let mut ours = base.clone();
+        ours[0].due = crate::Date::from_iso("2021-03-04");
+        let mut theirs = base.clone();
+        theirs[0].due = crate::Date::from_iso("2021-05-06");
+
+        let merged = merge(&base, &ours, &theirs, Side::Theirs);
+        assert_eq!(merged.conflicts.len(), 1);
+        let conflict = &merged.conflicts[0];
+        assert_eq!(conflict.field, "due");
+        assert_eq!(conflict.ours, "4/3/2021");
+        assert_eq!(conflict.theirs, "6/5/2021");
+        assert_eq!(merged.tasks[0].due, theirs[0].due);
+    }
+
+    #[test]
+    fn edit_against_removal_is_a_conflict() {
+        let base = vec![task("a", "a")];
+        let mut ours = base.clone();
+        ours[0].done = true;
+
+        let merged = merge(&base, &ours, &[], Side::Ours);
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].theirs, "removed");
+        assert_eq!(ids(&merged), ["a"]);
+    }
+}
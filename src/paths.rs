@@ -0,0 +1,88 @@
+use std::{env, path::Path};
+
+/// A resolved file path along with why it was chosen, see the `paths` subcommand
+pub struct Resolved {
+    pub path : String,
+    pub reason : String,
+}
+impl Resolved {
+    pub fn new(path : String, reason : &str) -> Resolved {
+        Resolved {
+            path,
+            reason : String::from(reason),
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        Path::new(&self.path).exists()
+    }
+}
+
+/// Reads an environment variable, treating an empty value as unset
+fn var(name : &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Either the XDG variable or `$HOME/<home_suffix>`, `.` if neither is set
+fn xdg_dir(xdg_var : &str, home_suffix : &str) -> Resolved {
+    if let Some(dir) = var(xdg_var) {
+        Resolved::new(dir, &format!("${} is set", xdg_var))
+    }
+    else if let Some(home) = var("HOME") {
+        Resolved::new(format!("{}/{}", home, home_suffix), &format!("${} is unset, using $HOME/{}", xdg_var, home_suffix))
+    }
+    else {
+        Resolved::new(String::from("."), &format!("neither ${} nor $HOME are set, using the current directory", xdg_var))
+    }
+}
+
+/// `$XDG_DATA_HOME`, defaults to `$HOME/.local/share`
+pub fn data_home() -> String {
+    xdg_dir("XDG_DATA_HOME", ".local/share").path
+}
+
+/// `$XDG_CONFIG_HOME`, defaults to `$HOME/.config`
+pub fn config_home() -> String {
+    xdg_dir("XDG_CONFIG_HOME", ".config").path
+}
+
+/**
+    The tasks file to use, in order of preference:
+    - the `--file` argument(or the `--list` argument, resolved by the caller)
+    - `$COOL_ORGANIZER_FILE`
+    - the default list from the config
+    - `$XDG_DATA_HOME/cool_organizer/tasks.<extension>`
+
+    Older versions used `$XDG_DATA_HOME/tasks.toml`, which other tools could be using as well,
+    so it's never read or written, see `old_data_file`.
+*/
+pub fn data_file(flag : Option<&str>, default_list : Option<Resolved>, extension : &str) -> Resolved {
+    if let Some(f) = flag {
+        return Resolved::new(String::from(f), "given with --file");
+    }
+    if let Some(f) = var("COOL_ORGANIZER_FILE") {
+        return Resolved::new(f, "$COOL_ORGANIZER_FILE is set");
+    }
+    if let Some(list) = default_list {
+        return list;
+    }
+
+    let dir = xdg_dir("XDG_DATA_HOME", ".local/share");
+    Resolved::new(format!("{}/cool_organizer/tasks.{}", dir.path, extension), &dir.reason)
+}
+
+/// `$XDG_DATA_HOME/tasks.toml`, where older versions kept the tasks, if there is such a file
+pub fn old_data_file() -> Option<String> {
+    let path = format!("{}/tasks.toml", data_home());
+    Some(path).filter(|p| Path::new(p).exists())
+}
+
+/// The config file, either the `--config` argument or `$XDG_CONFIG_HOME/cool_organizer/config.toml`
+pub fn config_file(flag : Option<&str>) -> Resolved {
+    if let Some(f) = flag {
+        return Resolved::new(String::from(f), "given with --config");
+    }
+
+    let dir = xdg_dir("XDG_CONFIG_HOME", ".config");
+    Resolved::new(format!("{}/cool_organizer/config.toml", dir.path), &dir.reason)
+}
@@ -0,0 +1,66 @@
+use std::io::{BufRead, Write, stdin, stdout};
+
+/**
+    The input was closed(Ctrl-D) or couldn't be read, the dialog stops and nothing is saved.
+    Ctrl-C ends the process right away, which doesn't save either.
+*/
+pub struct Aborted;
+
+/// Reads a line without its line break(`\n` or `\r\n`) and the spaces around it
+fn read_line() -> Result<String, Aborted> {
+    let mut line = String::new();
+
+    match stdin().lock().read_line(&mut line) {
+        Ok(0) => Err(Aborted),
+        Ok(_) => Ok(String::from(line.trim())),
+        Err(e) => {
+            eprintln!("couldn't read the input: {}", e);
+            Err(Aborted)
+        }
+    }
+}
+
+/// Asks for a line of text, `default` is shown in brackets and given back for an empty answer
+pub fn text(label : &str, default : Option<&str>) -> Result<String, Aborted> {
+    match default {
+        Some(d) => print!("{} [{}]: ", label, d),
+        None => print!("{}: ", label),
+    }
+    let _ = stdout().flush();
+
+    let answer = read_line()?;
+    match default {
+        Some(d) if answer.is_empty() => Ok(String::from(d)),
+        _ => Ok(answer),
+    }
+}
+
+/// Asks again until `parse` takes the answer, telling why it didn't
+pub fn ask<T>(label : &str, default : Option<&str>, parse : impl Fn(&str) -> Result<T, String>) -> Result<T, Aborted> {
+    loop {
+        match parse(&text(label, default)?) {
+            Ok(value) => return Ok(value),
+            Err(e) => println!("{}, try again", e),
+        }
+    }
+}
+
+/// A yes or no question, an empty answer is `default`
+pub fn confirm(label : &str, default : bool) -> Result<bool, Aborted> {
+    let label = format!("{} [{}]", label, if default { "Y/n" } else { "y/N" });
+
+    ask(&label, None, |answer| match answer.to_lowercase().as_str() {
+        "" => Ok(default),
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        _ => Err(format!("'{}' isn't yes or no", answer)),
+    })
+}
+
+/// A number from `min` to `max`
+pub fn number(label : &str, default : Option<&str>, min : i64, max : i64) -> Result<i64, Aborted> {
+    ask(label, default, |answer| match answer.parse::<i64>() {
+        Ok(n) if (min..=max).contains(&n) => Ok(n),
+        _ => Err(format!("'{}' isn't a number from {} to {}", answer, min, max)),
+    })
+}
@@ -0,0 +1,297 @@
+use serde_derive::{Serialize,Deserialize};
+use crate::{Date, DatePiece, Task};
+
+/**
+    Which csv column holds which field, kept in the `[csv]` table of the config.
+    An empty name leaves the field out. `date_format` is made of `d`, `m` and `y`
+    with any separators, like `d/m/y` or `y-m-d`.
+*/
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Columns {
+    pub id : String,
+    pub name : String,
+    pub category : String,
+    pub sub_category : String,
+    pub priority : String,
+    pub due : String,
+    pub done : String,
+    pub date_format : String,
+    pub delimiter : char,
+}
+impl Default for Columns {
+    fn default() -> Columns {
+        Columns {
+            id : String::from("id"),
+            name : String::from("name"),
+            category : String::from("category"),
+            sub_category : String::from("sub_category"),
+            priority : String::from("priority"),
+            due : String::from("due"),
+            done : String::from("done"),
+            date_format : String::from("d/m/y"),
+            delimiter : ',',
+        }
+    }
+}
+
+/// The fields a column can hold
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Id,
+    Name,
+    Category,
+    SubCategory,
+    Priority,
+    Due,
+    Done,
+}
+
+impl Columns {
+    fn fields(&self) -> [(Field, &str); 7] {
+        [
+            (Field::Id, &self.id),
+            (Field::Name, &self.name),
+            (Field::Category, &self.category),
+            (Field::SubCategory, &self.sub_category),
+            (Field::Priority, &self.priority),
+            (Field::Due, &self.due),
+            (Field::Done, &self.done),
+        ]
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.delimiter.is_ascii() {
+            Ok(())
+        }
+        else {
+            Err(format!("the csv delimiter has to be an ascii character, not '{}'", self.delimiter))
+        }
+    }
+
+    /// Reads a date written in `date_format`, two digit years are in the 2000s
+    fn parse_date(&self, s : &str) -> Option<Date> {
+        let mut rest = s.trim();
+        let (mut d, mut m, mut y) = (None, None, None);
+
+        for c in self.date_format.chars() {
+            match c {
+                'd' | 'm' | 'y' => {
+                    let len = rest.find(|c : char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                    let n = rest[..len].parse::<i64>().ok()?;
+                    match c {
+                        'd' => d = Some(n),
+                        'm' => m = Some(n),
+                        _ => y = Some(if len <= 2 { 2000 + n } else { n }),
+                    }
+                    rest = &rest[len..];
+                }
+                c => rest = rest.strip_prefix(c)?,
+            }
+        }
+
+        if !rest.is_empty() {
+            return None;
+        }
+
+        Date::from_ymd(y?, m?, d?)
+    }
+
+    fn write_date(&self, date : Date) -> String {
+        let date = match date.to_localdate() {
+            Some(d) => d,
+            None => return String::new(),
+        };
+
+        self.date_format.chars()
+            .map(|c| match c {
+                'd' => date.day().to_string(),
+                'm' => (date.month() as i32).to_string(),
+                'y' => date.year().to_string(),
+                c => c.to_string(),
+            })
+            .collect()
+    }
+}
+
+fn parse_priority(s : &str) -> Option<u8> {
+    match s.trim().to_lowercase().as_str() {
+        "" | "0" | "none" => Some(0),
+        "1" | "high" | "h" => Some(1),
+        "2" | "medium" | "m" => Some(2),
+        "3" | "low" | "l" => Some(3),
+        _ => None,
+    }
+}
+
+fn parse_done(s : &str) -> Option<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "" | "no" | "n" | "false" | "0" | "open" | "todo" => Some(false),
+        "x" | "yes" | "y" | "true" | "1" | "done" | "completed" => Some(true),
+        _ => None,
+    }
+}
+
+/**
+    Reads the tasks of a csv file with a header row, mapping the columns like `columns` says.
+    Gives the tasks of the rows that could be read along with an error for each row that couldn't.
+    The whole file fails when it can't be read or has no name column.
+*/
+pub fn parse(text : &str, columns : &Columns) -> Result<(Vec<Task>, Vec<String>), String> {
+    columns.check()?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(columns.delimiter as u8)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let header = reader.headers().map_err(|e| e.to_string())?.clone();
+    let mut found = Vec::new();
+    for (field, name) in columns.fields().iter().filter(|(_, name)| !name.is_empty()) {
+        match header.iter().position(|h| h.trim().eq_ignore_ascii_case(name.trim())) {
+            Some(at) => found.push((*field, *name, at)),
+            None if *field == Field::Name => return Err(format!("no '{}' column for the task names", name)),
+            None => {}
+        }
+    }
+
+    let mut tasks = Vec::new();
+    let mut errors = Vec::new();
+
+    for (n, record) in reader.records().enumerate() {
+        // the header is line 1
+        let row = n + 2;
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                errors.push(format!("row {}: {}", row, e));
+                continue;
+            }
+        };
+
+        let mut task = Task::imported("");
+        let mut row_errors = Vec::new();
+
+        for (field, column, at) in found.iter() {
+            let value = record.get(*at).unwrap_or_default().trim();
+            match field {
+                Field::Id => task.id = String::from(value),
+                Field::Name => task.name = String::from(value),
+                Field::Category => task.category = String::from(value),
+                Field::SubCategory => task.sub_category = String::from(value),
+                Field::Priority => match parse_priority(value) {
+                    Some(p) => task.priority = p,
+                    None => row_errors.push(format!("invalid priority '{}' in {}, expected 0-3 or high/medium/low", value, column)),
+                },
+                Field::Due if value.is_empty() => {}
+                Field::Due => match columns.parse_date(value) {
+                    Some(d) => task.due = Some(d),
+                    None => row_errors.push(format!("invalid date '{}' in {}, expected {}", value, column, columns.date_format)),
+                },
+                Field::Done => match parse_done(value) {
+                    Some(d) => task.done = d,
+                    None => row_errors.push(format!("invalid done value '{}' in {}, expected yes or no", value, column)),
+                },
+            }
+        }
+        if task.name.is_empty() {
+            row_errors.push(String::from("the name is empty"));
+        }
+
+        if row_errors.is_empty() {
+            tasks.push(task);
+        }
+        else {
+            errors.extend(row_errors.iter().map(|e| format!("row {}: {}", row, e)));
+        }
+    }
+
+    Ok((tasks, errors))
+}
+
+/// The tasks as csv, with the columns `columns` maps
+pub fn write(tasks : &[Task], columns : &Columns) -> Result<String, String> {
+    columns.check()?;
+    let fields : Vec<(Field, &str)> = columns.fields().iter()
+        .filter(|(_, name)| !name.is_empty())
+        .cloned()
+        .collect();
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(columns.delimiter as u8)
+        .from_writer(Vec::new());
+
+    writer.write_record(fields.iter().map(|(_, name)| *name)).map_err(|e| e.to_string())?;
+    for task in tasks {
+        let record = fields.iter().map(|(field, _)| match field {
+            Field::Id => task.id.clone(),
+            Field::Name => task.name.clone(),
+            Field::Category => task.category.clone(),
+            Field::SubCategory => task.sub_category.clone(),
+            Field::Priority => task.priority.to_string(),
+            Field::Due => task.due.map(|d| columns.write_date(d)).unwrap_or_default(),
+            Field::Done => String::from(if task.done { "yes" } else { "no" }),
+        });
+        writer.write_record(record).map_err(|e| e.to_string())?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_rows_are_reported_and_skipped() {
+        let text = "name,priority,due,done\n\
+            ok,1,25/10/2026,no\n\
+            bad priority,7,,no\n\
+            day out of range,0,257/1/2026,no\n\
+            month out of range,0,1/13/2026,no\n\
+            bad done,0,,maybe\n\
+            ,0,,no\n";
+
+        let (tasks, errors) = parse(text, &Columns::default()).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].name, "ok");
+        assert_eq!(tasks[0].due, Date::from_iso("2026-10-25"));
+        assert_eq!(errors.len(), 5);
+        assert!(errors[1].starts_with("row 4: invalid date '257/1/2026'"));
+    }
+
+    #[test]
+    fn missing_name_column_fails() {
+        assert!(parse("title,due\na,1/1/2026\n", &Columns::default()).is_err());
+    }
+
+    #[test]
+    fn dates_follow_the_format() {
+        let columns = Columns { date_format : String::from("y-m-d"), ..Columns::default() };
+        assert_eq!(columns.parse_date("26-10-25"), Date::from_iso("2026-10-25"));
+        assert_eq!(columns.parse_date("2026-2-30"), None);
+        assert_eq!(columns.parse_date("2026/10/25"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut done = Task::imported("b, with a comma");
+        done.id = String::from("2");
+        done.done = true;
+        done.priority = 3;
+        let tasks = vec![
+            Task {
+                id : String::from("1"),
+                category : String::from("work"),
+                sub_category : String::from("mail"),
+                due : Date::from_iso("2026-10-25"),
+                ..Task::imported("a")
+            },
+            done,
+        ];
+
+        let (parsed, errors) = parse(&write(&tasks, &Columns::default()).unwrap(), &Columns::default()).unwrap();
+        assert!(errors.is_empty());
+        assert!(parsed == tasks);
+    }
+}
@@ -0,0 +1,353 @@
+use rusqlite::{Connection, OptionalExtension, Row, Transaction, TransactionBehavior, params};
+use std::{fs, path::Path, time::Duration};
+use crate::{Date, FormatParams, Task, TasksManager, archive::{ArchivedTask, Reason}, storage::Storage, timestamp};
+
+/// How long to wait for another process writing to the database
+const BUSY_TIMEOUT : Duration = Duration::from_secs(5);
+
+/**
+    The schema, one step per version. `PRAGMA user_version` is how many steps a database
+    already went through, so only the newer ones are applied when it is opened.
+    A released step may already have run on someone's database, so schema changes go in a new step.
+*/
+const MIGRATIONS : [&str; 2] = [
+    // 1: tasks, the archive and the colors. `IF NOT EXISTS` as databases from before the
+    // migrations were versioned already have these tables
+    "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        category TEXT NOT NULL,
+        sub_category TEXT NOT NULL,
+        priority INTEGER NOT NULL,
+        done INTEGER NOT NULL,
+        due_year INTEGER,
+        due_day INTEGER,
+        created INTEGER,
+        modified INTEGER,
+        completed INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS tasks_category ON tasks (category, sub_category);
+    CREATE INDEX IF NOT EXISTS tasks_due ON tasks (due_year, due_day);
+    CREATE TABLE IF NOT EXISTS archive (
+        position INTEGER PRIMARY KEY,
+        reason TEXT NOT NULL,
+        date_year INTEGER NOT NULL,
+        date_day INTEGER NOT NULL,
+        id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        category TEXT NOT NULL,
+        sub_category TEXT NOT NULL,
+        priority INTEGER NOT NULL,
+        done INTEGER NOT NULL,
+        due_year INTEGER,
+        due_day INTEGER,
+        created INTEGER,
+        modified INTEGER,
+        completed INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS archive_name ON archive (name COLLATE NOCASE);
+    CREATE INDEX IF NOT EXISTS archive_date ON archive (date_year, date_day);
+    ",
+    // 2: the categories with their task counts, for scripts to read, and a history of the saves
+    "
+    CREATE TABLE categories (
+        category TEXT NOT NULL,
+        sub_category TEXT NOT NULL,
+        open INTEGER NOT NULL,
+        done INTEGER NOT NULL,
+        PRIMARY KEY (category, sub_category)
+    );
+    INSERT INTO categories
+        SELECT category, sub_category, SUM(NOT done), SUM(done) FROM tasks GROUP BY category, sub_category;
+    CREATE TABLE history (
+        revision INTEGER PRIMARY KEY,
+        time INTEGER NOT NULL,
+        summary TEXT NOT NULL
+    );
+    ",
+];
+
+/// The columns of a task, in the order `task_from` reads them
+const TASK_COLUMNS : &str = "id, name, category, sub_category, priority, done, due_year, due_day, created, modified, completed";
+
+fn sql_error(path : &str, e : rusqlite::Error) -> String {
+    format!("{}: {}", path, e)
+}
+
+/// Reads the task columns of a row, starting at column `at`
+fn task_from(row : &Row, at : usize) -> rusqlite::Result<Task> {
+    let due_year : Option<i64> = row.get(at + 6)?;
+    let due_day : Option<i64> = row.get(at + 7)?;
+
+    Ok(Task {
+        id : row.get(at)?,
+        name : row.get(at + 1)?,
+        category : row.get(at + 2)?,
+        sub_category : row.get(at + 3)?,
+        priority : row.get(at + 4)?,
+        done : row.get(at + 5)?,
+        due : due_year.zip(due_day).map(|(year, day)| Date::ymd(year, day)),
+        created : row.get(at + 8)?,
+        modified : row.get(at + 9)?,
+        completed : row.get(at + 10)?,
+    })
+}
+
+/**
+    Tasks kept in a sqlite database, for lists too big to rewrite a text file on every save.
+    The archive lives in the same database, so searching it doesn't read every archived task,
+    and the colors are kept as toml in the `meta` table when there is no config file for them.
+*/
+pub struct Sqlite {
+    path : String,
+}
+impl Sqlite {
+    pub fn new(path : &str) -> Sqlite {
+        Sqlite {
+            path : String::from(path),
+        }
+    }
+
+    /// Opens the database, creating it and bringing its schema up to date if needed
+    fn connect(&self) -> Result<Connection, String> {
+        if let Some(dir) = Path::new(&self.path).parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+        }
+
+        let mut conn = Connection::open(&self.path).map_err(|e| format!("couldn't open {}: {}", self.path, e))?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| sql_error(&self.path, e))?;
+        self.migrate(&mut conn)?;
+
+        Ok(conn)
+    }
+
+    /// Applies the migrations the database is missing, all of them or none
+    fn migrate(&self, conn : &mut Connection) -> Result<(), String> {
+        let error = |e| sql_error(&self.path, e);
+        let version = |conn : &Connection| conn.query_row("PRAGMA user_version", [], |row| row.get::<_, usize>(0));
+
+        if version(conn).map_err(error)? == MIGRATIONS.len() {
+            return Ok(());
+        }
+
+        // Checked again once locked, another process might have migrated it in the meantime
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(error)?;
+        let from = version(&tx).map_err(error)?;
+        if from > MIGRATIONS.len() {
+            return Err(format!(
+                "{} has schema version {} but this version of cool_organizer only knows up to {}, update it",
+                self.path, from, MIGRATIONS.len()
+            ));
+        }
+
+        for (i, step) in MIGRATIONS.iter().enumerate().skip(from) {
+            tx.execute_batch(step).map_err(|e| format!("{}: migration {} failed: {}", self.path, i + 1, e))?;
+            tx.pragma_update(None, "user_version", i + 1).map_err(error)?;
+        }
+        tx.commit().map_err(error)
+    }
+
+    /// The summaries of the last `count` saves with their time, newest first
+    pub fn history(&self, count : usize) -> Result<Vec<(i64, String)>, String> {
+        let conn = match self.connect_existing()? {
+            Some(c) => c,
+            None => return Ok(Vec::new()),
+        };
+        let error = |e| sql_error(&self.path, e);
+
+        let mut select = conn.prepare("SELECT time, summary FROM history ORDER BY revision DESC LIMIT ?1").map_err(error)?;
+        let rows = select.query_map([count as i64], |row| Ok((row.get(0)?, row.get(1)?)));
+
+        rows.and_then(|rows| rows.collect()).map_err(error)
+    }
+
+    /// Opens the database only if it exists, reading never creates it
+    fn connect_existing(&self) -> Result<Option<Connection>, String> {
+        if Path::new(&self.path).exists() {
+            self.connect().map(Some)
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    fn revision(conn : &Connection) -> rusqlite::Result<Option<u64>> {
+        conn.query_row("SELECT value FROM meta WHERE key = 'revision'", [], |row| row.get::<_, String>(0))
+            .optional()
+            .map(|r| r.and_then(|r| r.parse().ok()))
+    }
+
+    /// The colors kept in the database, as toml
+    fn read_colors(conn : &Connection) -> rusqlite::Result<Option<String>> {
+        conn.query_row("SELECT value FROM meta WHERE key = 'colors'", [], |row| row.get(0)).optional()
+    }
+
+    fn read_tasks(conn : &Connection) -> rusqlite::Result<Vec<Task>> {
+        let mut select = conn.prepare(&format!("SELECT {} FROM tasks ORDER BY position", TASK_COLUMNS))?;
+        let rows = select.query_map([], |row| task_from(row, 0))?;
+        rows.collect()
+    }
+
+    fn append_archive(tx : &Transaction, archived : &[ArchivedTask]) -> rusqlite::Result<()> {
+        let next : i64 = tx.query_row("SELECT COALESCE(MAX(position) + 1, 0) FROM archive", [], |row| row.get(0))?;
+        let mut insert = tx.prepare(&format!(
+            "INSERT INTO archive (position, reason, date_year, date_day, {}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            TASK_COLUMNS
+        ))?;
+
+        for (i, a) in archived.iter().enumerate() {
+            let reason = match a.reason {
+                Reason::Done => "done",
+                Reason::Removed => "removed",
+            };
+            let t = &a.task;
+            insert.execute(params![
+                next + i as i64, reason, a.date.year, a.date.day,
+                t.id, t.name, t.category, t.sub_category, t.priority, t.done,
+                t.due.map(|d| d.year), t.due.map(|d| d.day), t.created, t.modified, t.completed,
+            ])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Storage for Sqlite {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn load(&self) -> Result<(TasksManager, Option<u64>), String> {
+        let mut conn = match self.connect_existing()? {
+            Some(c) => c,
+            None => return Ok((TasksManager::default(), None)),
+        };
+        let error = |e| sql_error(&self.path, e);
+        // One read transaction, so the revision is the one of the tasks read
+        let tx = conn.transaction().map_err(error)?;
+
+        let mut tasks = TasksManager::default();
+        if let Some(colors) = Sqlite::read_colors(&tx).map_err(error)? {
+            tasks.colors = toml::from_str::<FormatParams>(&colors).map_err(|e| format!("couldn't load the colors from {}: {}", self.path, e))?;
+        }
+        tasks.tasks = Sqlite::read_tasks(&tx).map_err(error)?;
+
+        Ok((tasks, Sqlite::revision(&tx).map_err(error)?))
+    }
+
+    fn version(&self) -> Option<u64> {
+        let conn = self.connect_existing().ok()??;
+        Sqlite::revision(&conn).ok()?
+    }
+
+    fn save(&self, tasks : &TasksManager, archived : &[ArchivedTask], summary : &str, expected : Option<Option<u64>>) -> Result<Option<u64>, String> {
+        let mut conn = self.connect()?;
+        let error = |e| sql_error(&self.path, e);
+
+        // Immediate, so nobody else can write between the check and the save
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(error)?;
+        let current = Sqlite::revision(&tx).map_err(error)?;
+        if expected.is_some_and(|e| e != current) {
+            return Err(format!("{} was changed since it was loaded, nothing was saved", self.path));
+        }
+
+        // Like the toml format, the colors stay out when they are kept in the config file
+        let colors = match tasks.config_path {
+            Some(_) => None,
+            None => Some(toml::to_string(&tasks.colors).map_err(|e| e.to_string())?),
+        };
+        // Nothing changed, keep the revision so a save like `-r` running from conky
+        // doesn't look like a change to everyone else and adds no history
+        if archived.is_empty() && Sqlite::read_colors(&tx).map_err(error)? == colors && Sqlite::read_tasks(&tx).map_err(error)? == tasks.tasks {
+            return Ok(current);
+        }
+
+        Sqlite::append_archive(&tx, archived).map_err(error)?;
+
+        tx.execute("DELETE FROM tasks", []).map_err(error)?;
+        {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO tasks (position, {}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                TASK_COLUMNS
+            )).map_err(error)?;
+            for (i, t) in tasks.tasks.iter().enumerate() {
+                insert.execute(params![
+                    i as i64, t.id, t.name, t.category, t.sub_category, t.priority, t.done,
+                    t.due.map(|d| d.year), t.due.map(|d| d.day), t.created, t.modified, t.completed,
+                ]).map_err(error)?;
+            }
+        }
+
+        match colors {
+            None => tx.execute("DELETE FROM meta WHERE key = 'colors'", []),
+            Some(colors) => tx.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('colors', ?1)", [colors]),
+        }.map_err(error)?;
+
+        tx.execute_batch("
+            DELETE FROM categories;
+            INSERT INTO categories
+                SELECT category, sub_category, SUM(NOT done), SUM(done) FROM tasks GROUP BY category, sub_category;
+        ").map_err(error)?;
+
+        let revision = current.unwrap_or(0) + 1;
+        tx.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('revision', ?1)", [revision.to_string()]).map_err(error)?;
+        if !summary.is_empty() {
+            tx.execute("INSERT INTO history (revision, time, summary) VALUES (?1, ?2, ?3)", params![revision, timestamp::now(), summary]).map_err(error)?;
+        }
+        tx.commit().map_err(error)?;
+
+        Ok(Some(revision))
+    }
+
+    fn load_archive(&self) -> Result<Vec<ArchivedTask>, String> {
+        let conn = match self.connect_existing()? {
+            Some(c) => c,
+            None => return Ok(Vec::new()),
+        };
+        let error = |e| sql_error(&self.path, e);
+
+        let mut select = conn.prepare(&format!("SELECT reason, date_year, date_day, {} FROM archive ORDER BY position", TASK_COLUMNS)).map_err(error)?;
+        let rows = select.query_map([], |row| Ok(ArchivedTask {
+            reason : if row.get::<_, String>(0)? == "removed" { Reason::Removed } else { Reason::Done },
+            date : Date::ymd(row.get(1)?, row.get(2)?),
+            task : task_from(row, 3)?,
+        }));
+
+        rows.and_then(|rows| rows.collect()).map_err(error)
+    }
+
+    fn save_archive(&self, archived : &[ArchivedTask]) -> Result<(), String> {
+        let mut conn = self.connect()?;
+        let error = |e| sql_error(&self.path, e);
+
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(error)?;
+        tx.execute("DELETE FROM archive", []).map_err(error)?;
+        Sqlite::append_archive(&tx, archived).map_err(error)?;
+        tx.commit().map_err(error)
+    }
+
+    /// Searches in sqlite instead of loading the whole archive, the positions are the indexes
+    fn search_archive(&self, query : &str) -> Result<Vec<usize>, String> {
+        let conn = match self.connect_existing()? {
+            Some(c) => c,
+            None => return Ok(Vec::new()),
+        };
+        let error = |e| sql_error(&self.path, e);
+
+        let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let mut select = conn.prepare(
+            "SELECT position FROM archive
+            WHERE name LIKE ?1 ESCAPE '\\' OR category LIKE ?1 ESCAPE '\\' OR sub_category LIKE ?1 ESCAPE '\\'
+            ORDER BY position"
+        ).map_err(error)?;
+        let rows = select.query_map([pattern], |row| row.get::<_, i64>(0).map(|p| p as usize));
+
+        rows.and_then(|rows| rows.collect()).map_err(error)
+    }
+}
@@ -0,0 +1,160 @@
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use crate::{Date, Task, agenda, archive::{ArchivedTask, Reason}, timestamp::{self, DAY, week_start}};
+
+/// A number for a day(or the week starting on it)
+#[derive(Serialize)]
+pub struct Count {
+    /// `yyyy-mm-dd`
+    pub date : String,
+    pub count : usize,
+}
+
+#[derive(Serialize)]
+pub struct Burndown {
+    pub category : String,
+    /// Tasks still open at the end of each day
+    pub open : Vec<Count>,
+}
+
+/// Numbers about the tasks, see `Stats::new`
+#[derive(Serialize)]
+pub struct Stats {
+    pub completed_per_day : Vec<Count>,
+    pub completed_per_week : Vec<Count>,
+    /// From being added to being done
+    pub average_lead_time_days : Option<f64>,
+    pub overdue_per_category : BTreeMap<String, usize>,
+    pub burndown : Option<Burndown>,
+}
+
+/// A task with when it was closed, done or removed
+struct Entry<'a> {
+    task : &'a Task,
+    closed : Option<i64>,
+    done : bool,
+}
+
+/// `yyyy-mm-dd` of a day since the epoch
+fn iso(day : i64) -> String {
+    Date::from_timestamp(day * DAY).to_iso().unwrap_or_default()
+}
+
+impl Stats {
+    /**
+        Collects the stats of the tasks and the archived tasks.
+        `days` and `weeks` are how far back the completion counts go, and the burndown
+        is given as (category, first day, last day) in seconds since the epoch.
+    */
+    pub fn new(tasks : &[Task], archived : &[ArchivedTask], days : i64, weeks : i64, burndown : Option<(&str, i64, i64)>) -> Stats {
+        let mut entries : Vec<Entry> = tasks.iter()
+            .map(|t| Entry { task : t, closed : if t.done { t.completed } else { None }, done : t.done })
+            .collect();
+        entries.extend(archived.iter().map(|a| {
+            let archived_at = a.date.timestamp();
+            match a.reason {
+                Reason::Done => Entry { task : &a.task, closed : a.task.completed.or(archived_at), done : true },
+                Reason::Removed => Entry { task : &a.task, closed : archived_at, done : false },
+            }
+        }));
+
+        let today = timestamp::now().div_euclid(DAY);
+        let completed_days : Vec<i64> = entries.iter()
+            .filter(|e| e.done)
+            .filter_map(|e| e.closed)
+            .map(|c| c.div_euclid(DAY))
+            .collect();
+
+        let completed_per_day = (today - days + 1..=today)
+            .map(|day| Count { date : iso(day), count : completed_days.iter().filter(|d| **d == day).count() })
+            .collect();
+
+        let this_week = week_start(today);
+        let completed_per_week = (0..weeks).rev()
+            .map(|w| this_week - w * 7)
+            .map(|start| Count { date : iso(start), count : completed_days.iter().filter(|d| week_start(**d) == start).count() })
+            .collect();
+
+        let leads : Vec<i64> = entries.iter()
+            .filter(|e| e.done)
+            .filter_map(|e| Some(e.closed? - e.task.created?))
+            .collect();
+        let average_lead_time_days = if leads.is_empty() {
+            None
+        }
+        else {
+            Some(leads.iter().sum::<i64>() as f64 / leads.len() as f64 / DAY as f64)
+        };
+
+        let mut overdue_per_category = BTreeMap::new();
+        for t in tasks.iter().filter(|t| !t.done && agenda::days_left(t).unwrap_or(0) < 0) {
+            *overdue_per_category.entry(t.category.clone()).or_insert(0) += 1;
+        }
+
+        let burndown = burndown.map(|(category, from, to)| {
+            let open = (from.div_euclid(DAY)..=to.div_euclid(DAY))
+                .map(|day| {
+                    let end = (day + 1) * DAY;
+                    let count = entries.iter()
+                        .filter(|e| e.task.category == category)
+                        .filter(|e| e.task.created.map(|c| c < end).unwrap_or(true))
+                        .filter(|e| e.closed.map(|c| c >= end).unwrap_or(true))
+                        .count();
+                    Count { date : iso(day), count }
+                })
+                .collect();
+
+            Burndown { category : String::from(category), open }
+        });
+
+        Stats {
+            completed_per_day,
+            completed_per_week,
+            average_lead_time_days,
+            overdue_per_category,
+            burndown,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// The stats as text tables with small bar charts
+    pub fn to_text(&self) -> String {
+        let bar = |n : usize| if n == 0 { String::new() } else { format!(" {}", "#".repeat(n)) };
+        let mut s = String::from("Completed per day:\n");
+        for c in self.completed_per_day.iter() {
+            s.push_str(&format!("  {}  {:3}{}\n", c.date, c.count, bar(c.count)));
+        }
+
+        s.push_str("\nCompleted per week:\n");
+        for c in self.completed_per_week.iter() {
+            s.push_str(&format!("  week of {}  {:3}{}\n", c.date, c.count, bar(c.count)));
+        }
+
+        s.push_str("\nAverage lead time: ");
+        match self.average_lead_time_days {
+            Some(d) => s.push_str(&format!("{:.1} days\n", d)),
+            None => s.push_str("unknown\n"),
+        }
+
+        s.push_str("\nOverdue per category:\n");
+        if self.overdue_per_category.is_empty() {
+            s.push_str("  nothing is overdue\n");
+        }
+        for (cat, count) in self.overdue_per_category.iter() {
+            let cat = if cat.is_empty() { "(no category)" } else { cat };
+            s.push_str(&format!("  {:20} {:3}\n", cat, count));
+        }
+
+        if let Some(b) = &self.burndown {
+            s.push_str(&format!("\nBurndown for {}:\n", b.category));
+            for c in b.open.iter() {
+                s.push_str(&format!("  {}  {:3}{}\n", c.date, c.count, bar(c.count)));
+            }
+        }
+
+        s
+    }
+}
@@ -0,0 +1,224 @@
+use std::{fs, path::Path};
+use crate::{Format, TasksManager, archive::ArchivedTask, journal::Journal, lock::{self, FileLock}, sqlite::Sqlite};
+
+/// How a list is stored, picked from the file name or `--format`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// A text file in one of the formats
+    File(Format),
+    /// A sqlite database, see `sqlite::Sqlite`
+    Sqlite,
+}
+impl Default for Backend {
+    fn default() -> Backend {
+        Backend::File(Format::default())
+    }
+}
+
+/// Extensions of sqlite databases
+const SQLITE_EXTENSIONS : [&str; 3] = ["db", "sqlite", "sqlite3"];
+
+/// The file of a `--file` value, without the `sqlite:` or `sqlite://` in front of databases
+pub fn file_path(file : &str) -> &str {
+    match file.strip_prefix("sqlite:") {
+        Some(path) => path.strip_prefix("//").unwrap_or(path),
+        None => file,
+    }
+}
+
+impl Backend {
+    /**
+        How the list in `file` is stored. `sqlite:path` and `sqlite://path` are databases,
+        like files ending in `.db`, `.sqlite` or `.sqlite3`. Anything else is a text file
+        in the format of its extension, unless `format` says otherwise.
+    */
+    pub fn detect(file : &str, format : Option<Format>) -> Result<Backend, String> {
+        if file.starts_with("sqlite:") {
+            return Ok(Backend::Sqlite);
+        }
+        if let Some(format) = format {
+            return Ok(Backend::File(format));
+        }
+
+        let extension = Path::new(file).extension().and_then(|e| e.to_str()).unwrap_or_default();
+        if SQLITE_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+            Ok(Backend::Sqlite)
+        }
+        else {
+            Format::from_path(file).map(Backend::File)
+        }
+    }
+}
+
+/// The file and backend of a `--file` value, see `Backend::detect`
+pub fn locate(file : &str, format : Option<Format>) -> Result<(String, Backend), String> {
+    Backend::detect(file, format).map(|b| (String::from(file_path(file)), b))
+}
+
+/// Opens the storage of a list kept at `path`
+pub fn open(path : &str, backend : Backend) -> Box<dyn Storage> {
+    match backend {
+        Backend::File(format) => Box::new(TextFile { path : String::from(path), format }),
+        Backend::Sqlite => Box::new(Sqlite::new(path)),
+    }
+}
+
+/**
+    Converts a list kept in a text file into a sqlite database next to it(`tasks.db` for `tasks.toml`),
+    archive included. The old tasks and archive files are kept with `.bak` added, and the journal
+    is renamed to follow the list. Gives the path of the database.
+*/
+pub fn convert_to_sqlite(tasks : &TasksManager) -> Result<String, String> {
+    let file = match tasks.backend {
+        Backend::File(_) => TextFile { path : tasks.path.clone(), format : Format::default() },
+        Backend::Sqlite => return Err(format!("{} is already a sqlite database", tasks.path)),
+    };
+    let db = Path::new(&tasks.path).with_extension("db").to_string_lossy().into_owned();
+    if Path::new(&db).exists() {
+        return Err(format!("{} already exists, nothing was converted", db));
+    }
+
+    let mut archived = file.load_archive()?;
+    archived.extend(tasks.archived.iter().cloned());
+    Sqlite::new(&db).save(tasks, &archived, &format!("Convert from {}", tasks.path), Some(None))?;
+
+    let renames = [
+        (tasks.path.clone(), format!("{}.bak", tasks.path)),
+        (file.archive_path(), format!("{}.bak", file.archive_path())),
+        (Journal::path_for(&tasks.path), Journal::path_for(&db)),
+    ];
+    for (from, to) in renames.iter().filter(|(from, _)| Path::new(from).exists()) {
+        fs::rename(from, to).map_err(|e| format!("converted to {} but couldn't move {}: {}", db, from, e))?;
+    }
+
+    Ok(db)
+}
+
+/**
+    Where the tasks of a list are kept. The journal and git history stay in files next to `path`
+    whatever the storage is, while the tasks and the archive are up to it.
+*/
+pub trait Storage {
+    fn path(&self) -> &str;
+
+    /// Reads the tasks, their colors and the version they are at, nothing stored yet is an empty manager
+    fn load(&self) -> Result<(TasksManager, Option<u64>), String>;
+
+    /// Changes every time the tasks are saved, `None` when nothing is stored yet
+    fn version(&self) -> Option<u64>;
+
+    /**
+        Saves the tasks and moves the tasks waiting for it to the archive, all at once.
+        `summary` describes the changes, for storages keeping their own history.
+        With `expected`, fails without writing anything if the version isn't that anymore.
+        Gives the new version.
+    */
+    fn save(&self, tasks : &TasksManager, archived : &[ArchivedTask], summary : &str, expected : Option<Option<u64>>) -> Result<Option<u64>, String>;
+
+    fn load_archive(&self) -> Result<Vec<ArchivedTask>, String>;
+
+    /// Replaces the whole archive, after restoring tasks from it
+    fn save_archive(&self, archived : &[ArchivedTask]) -> Result<(), String>;
+
+    /// Positions of the archived tasks with `query` in their name or categories, ignoring case
+    fn search_archive(&self, query : &str) -> Result<Vec<usize>, String> {
+        let query = query.to_lowercase();
+
+        Ok(self.load_archive()?.iter()
+            .enumerate()
+            .filter(|(_, a)| [&a.task.name, &a.task.category, &a.task.sub_category].iter()
+                .any(|s| s.to_lowercase().contains(&query)))
+            .map(|(i, _)| i)
+            .collect())
+    }
+}
+
+/// A text file in one of the formats, with the archive in a toml file next to it
+pub struct TextFile {
+    path : String,
+    format : Format,
+}
+
+/// The archive file, `tasks.archive.toml` for `tasks.toml`
+#[derive(Default, serde_derive::Serialize, serde_derive::Deserialize)]
+struct ArchiveFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tasks : Vec<ArchivedTask>,
+}
+
+impl TextFile {
+    pub fn archive_path(&self) -> String {
+        let p = Path::new(&self.path);
+        let stem = p.file_stem().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+        p.with_file_name(format!("{}.archive.toml", stem)).to_string_lossy().into_owned()
+    }
+
+    fn write_archive(&self, archived : &[ArchivedTask]) -> Result<(), String> {
+        let path = self.archive_path();
+        let text = toml::to_string(&ArchiveFile { tasks : archived.to_vec() }).map_err(|e| e.to_string())?;
+
+        let _lock = FileLock::exclusive(&path)?;
+        fs::write(&path, text).map_err(|e| format!("couldn't save {}: {}", path, e))
+    }
+}
+
+impl Storage for TextFile {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn load(&self) -> Result<(TasksManager, Option<u64>), String> {
+        let lock = FileLock::shared(&self.path)?;
+        let file = fs::read_to_string(&self.path).ok();
+        drop(lock);
+
+        let tasks = self.format.parse(file.as_deref().unwrap_or_default()).map_err(|e| format!("couldn't load {}: {}", self.path, e))?;
+        Ok((tasks, lock::content_hash(file.as_deref())))
+    }
+
+    fn version(&self) -> Option<u64> {
+        let _lock = FileLock::shared(&self.path);
+        lock::content_hash(fs::read_to_string(&self.path).ok().as_deref())
+    }
+
+    fn save(&self, tasks : &TasksManager, archived : &[ArchivedTask], _summary : &str, expected : Option<Option<u64>>) -> Result<Option<u64>, String> {
+        let text = self.format.write(tasks)?;
+
+        if let Some(dir) = Path::new(&self.path).parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+        }
+
+        let _lock = FileLock::exclusive(&self.path)?;
+        let current = lock::content_hash(fs::read_to_string(&self.path).ok().as_deref());
+        if expected.is_some_and(|e| e != current) {
+            return Err(format!("{} was changed since it was loaded, nothing was saved", self.path));
+        }
+
+        // Archive first, if saving the tasks fails they are still in the tasks file as well
+        if !archived.is_empty() {
+            let mut archive = self.load_archive()?;
+            archive.extend(archived.iter().cloned());
+            self.write_archive(&archive)?;
+        }
+
+        fs::write(&self.path, &text).map_err(|e| format!("couldn't save {}: {}", self.path, e))?;
+
+        Ok(lock::content_hash(Some(&text)))
+    }
+
+    fn load_archive(&self) -> Result<Vec<ArchivedTask>, String> {
+        let path = self.archive_path();
+
+        let lock = FileLock::shared(&path)?;
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        drop(lock);
+
+        toml::from_str::<ArchiveFile>(&text)
+            .map(|a| a.tasks)
+            .map_err(|e| format!("couldn't load {}: {}", path, e))
+    }
+
+    fn save_archive(&self, archived : &[ArchivedTask]) -> Result<(), String> {
+        self.write_archive(archived)
+    }
+}
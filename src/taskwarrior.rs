@@ -0,0 +1,146 @@
+use serde_derive::{Serialize,Deserialize};
+use crate::{Date, Task, timestamp::{self, DAY}};
+
+/// A task like Taskwarrior's `task export` writes them, the other fields are ignored
+#[derive(Serialize, Deserialize)]
+struct TwTask {
+    uuid : String,
+    description : String,
+    status : String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    project : Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags : Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end : Option<String>,
+}
+
+impl TwTask {
+    fn to_task(&self, n : usize) -> Result<Task, String> {
+        let time = |name : &str, value : &Option<String>| match value {
+            Some(v) => timestamp::parse_compact(v)
+                .map(Some)
+                .ok_or(format!("task {}: invalid {} '{}', expected yyyymmddThhmmssZ", n, name, v)),
+            None => Ok(None),
+        };
+
+        let mut task = Task::new(self.description.trim());
+        task.id = self.uuid.clone();
+        task.category = self.project.clone().unwrap_or_default();
+        task.sub_category = self.tags.first().cloned().unwrap_or_default();
+        task.priority = match self.priority.as_deref() {
+            Some("H") => 1,
+            Some("M") => 2,
+            Some("L") => 3,
+            _ => 0,
+        };
+        task.done = self.status == "completed";
+        // Dates without a time are local midnights in utc, so the nearest midnight is the day
+        task.due = time("due", &self.due)?.map(|due| Date::from_timestamp((due + DAY / 2).div_euclid(DAY) * DAY));
+        task.created = time("entry", &self.entry)?;
+        task.modified = time("modified", &self.modified)?;
+        task.completed = if task.done { time("end", &self.end)? } else { None };
+
+        Ok(task)
+    }
+
+    fn from_task(task : &Task) -> TwTask {
+        TwTask {
+            uuid : task.id.clone(),
+            description : String::from(task.name.trim()),
+            status : String::from(if task.done { "completed" } else { "pending" }),
+            project : Some(String::from(task.category.trim())).filter(|p| !p.is_empty()),
+            tags : Some(task.sub_category.split_whitespace().collect::<Vec<_>>().join("_")).into_iter().filter(|t| !t.is_empty()).collect(),
+            priority : match task.priority {
+                1 => Some(String::from("H")),
+                2 => Some(String::from("M")),
+                3 => Some(String::from("L")),
+                _ => None,
+            },
+            due : task.due.and_then(|d| d.timestamp()).map(timestamp::compact),
+            entry : task.created.map(timestamp::compact),
+            modified : task.modified.map(timestamp::compact),
+            end : task.completed.filter(|_| task.done).map(timestamp::compact),
+        }
+    }
+}
+
+/**
+    Reads Taskwarrior's json export, either an array or an object per line.
+    The project is the category and the first tag the sub category, deleted tasks are left out.
+*/
+pub fn parse(text : &str) -> Result<Vec<Task>, String> {
+    let tw_tasks : Vec<TwTask> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text).map_err(|e| e.to_string())?
+    }
+    else {
+        text.lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(n, l)| serde_json::from_str(l).map_err(|e| format!("line {}: {}", n + 1, e)))
+            .collect::<Result<_, _>>()?
+    };
+
+    tw_tasks.iter()
+        .enumerate()
+        .filter(|(_, t)| t.status != "deleted")
+        .map(|(n, t)| t.to_task(n + 1))
+        .collect()
+}
+
+/// The tasks as a json array `task import` can read
+pub fn write(tasks : &[Task]) -> String {
+    let tw_tasks : Vec<TwTask> = tasks.iter().map(TwTask::from_task).collect();
+    serde_json::to_string_pretty(&tw_tasks).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut done = Task::new("b");
+        done.done = true;
+        done.priority = 3;
+        done.completed = Some(done.created.unwrap() + 60);
+        let tasks = vec![
+            Task {
+                category : String::from("work"),
+                sub_category : String::from("mail"),
+                priority : 1,
+                due : Date::from_iso("2026-10-25"),
+                ..Task::new("a")
+            },
+            done,
+        ];
+
+        assert!(parse(&write(&tasks)).unwrap() == tasks);
+    }
+
+    #[test]
+    fn reads_one_task_per_line_without_deleted_ones() {
+        let text = r#"{"uuid":"1","description":"a","status":"pending","project":"work","tags":["mail","x"],"due":"20261024T220000Z"}
+{"uuid":"2","description":"b","status":"deleted"}
+"#;
+        let tasks = parse(text).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!((tasks[0].category.as_str(), tasks[0].sub_category.as_str()), ("work", "mail"));
+        // A local midnight in utc is the nearest day
+        assert_eq!(tasks[0].due, Date::from_iso("2026-10-25"));
+    }
+
+    #[test]
+    fn bad_times_fail() {
+        assert!(parse(r#"[{"uuid":"1","description":"a","status":"pending","due":"20261301T000000Z"}]"#).is_err());
+        assert!(parse("{not json}").is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use datetime::{DatePiece, Instant, LocalDateTime, LocalTime, TimePiece};
+use crate::Date;
+
+/// Seconds in a day, dates are kept as the midnight starting them
+pub const DAY : i64 = 60 * 60 * 24;
+
+/// Seconds since the epoch, what the task timestamps(`created`, `modified`, `completed`) hold
+pub fn now() -> i64 {
+    LocalDateTime::now().to_instant().seconds()
+}
+
+/// The monday of the week `day`(in days since the epoch, which was a thursday) is in
+pub fn week_start(day : i64) -> i64 {
+    day - (day + 3).rem_euclid(7)
+}
+
+/// `d/m/y h:m`
+pub fn format(time : i64) -> String {
+    let t = LocalDateTime::from_instant(Instant::at(time));
+    format!("{}/{}/{} {:02}:{:02}", t.day(), t.month() as i32, t.year(), t.hour(), t.minute())
+}
+
+/// How long ago, like `today` or `3 days ago`
+pub fn ago(time : i64) -> String {
+    match (now() - time) / DAY {
+        d if d <= 0 => String::from("today"),
+        1 => String::from("yesterday"),
+        d => format!("{} days ago", d),
+    }
+}
+
+/// `yyyymmddThhmmssZ`, the utc time iCalendar and Taskwarrior use
+pub fn compact(time : i64) -> String {
+    let t = LocalDateTime::at(time);
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        t.year(), t.month() as i32, t.day(), t.hour(), t.minute(), t.second())
+}
+
+/// Reads a `compact` time, the `Z` is optional
+pub fn parse_compact(s : &str) -> Option<i64> {
+    let number = |from : usize, to : usize| s.get(from..to).and_then(|n| n.parse::<i64>().ok());
+    if s.get(8..9) != Some("T") || !(s.len() == 15 || s.len() == 16 && s.ends_with('Z')) {
+        return None;
+    }
+
+    let date = Date::from_ymd(number(0, 4)?, number(4, 6)?, number(6, 8)?)?.to_localdate()?;
+    let time = LocalTime::hms(number(9, 11)? as i8, number(11, 13)? as i8, number(13, 15)? as i8).ok()?;
+
+    Some(LocalDateTime::new(date, time).to_instant().seconds())
+}
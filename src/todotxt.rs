@@ -0,0 +1,183 @@
+use crate::{Date, Task};
+
+fn timestamp_iso(time : i64) -> Option<String> {
+    Date::from_timestamp(time).to_iso()
+}
+
+/// `A` to `C` are priorities 1 to 3, the lower ones are all 3
+fn priority_of(letter : &str) -> Option<u8> {
+    match letter.as_bytes() {
+        [p @ b'A'..=b'Z'] => Some((p - b'A' + 1).min(3)),
+        _ => None,
+    }
+}
+
+/// A priority written as `(A)`
+fn parse_priority(s : &str) -> Option<u8> {
+    s.strip_prefix('(').and_then(|s| s.strip_suffix(')')).and_then(priority_of)
+}
+
+fn priority_letter(p : u8) -> Option<char> {
+    match p {
+        1..=3 => Some((b'A' + p - 1) as char),
+        _ => None,
+    }
+}
+
+/// Categories can't have spaces in a `+project` or `@context`
+fn tag(s : &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/**
+    Reads a todo.txt file. The first `+project` is the category and the first `@context`
+    the sub category, `due:` and `id:` keys are read and `pri:` keeps the priority of done tasks.
+    Other projects, contexts and keys are left in the name.
+*/
+pub fn parse(text : &str) -> Result<Vec<Task>, String> {
+    let mut tasks = Vec::new();
+
+    for (n, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace().peekable();
+        if words.peek().is_none() {
+            continue;
+        }
+
+        let mut task = Task::imported("");
+
+        if words.peek() == Some(&"x") {
+            words.next();
+            task.done = true;
+            if let Some(completed) = words.peek().and_then(|w| Date::from_iso(w)) {
+                words.next();
+                task.completed = completed.timestamp();
+            }
+        }
+        else if let Some(p) = words.peek().and_then(|w| parse_priority(w)) {
+            words.next();
+            task.priority = p;
+        }
+        if let Some(created) = words.peek().and_then(|w| Date::from_iso(w)) {
+            words.next();
+            task.created = created.timestamp();
+        }
+
+        let mut name = Vec::new();
+        for word in words {
+            match word.split_once(':') {
+                Some(("due", d)) => match Date::from_iso(d) {
+                    Some(d) => task.due = Some(d),
+                    None => return Err(format!("line {}: invalid due date '{}', expected yyyy-mm-dd", n + 1, d)),
+                },
+                Some(("id", id)) if !id.is_empty() => task.id = String::from(id),
+                Some(("pri", p)) => match priority_of(p) {
+                    Some(p) => task.priority = p,
+                    None => name.push(word),
+                },
+                _ if word.len() > 1 && word.starts_with('+') && task.category.is_empty() => task.category = String::from(&word[1..]),
+                _ if word.len() > 1 && word.starts_with('@') && task.sub_category.is_empty() => task.sub_category = String::from(&word[1..]),
+                _ => name.push(word),
+            }
+        }
+        task.name = name.join(" ");
+
+        tasks.push(task);
+    }
+
+    Ok(tasks)
+}
+
+/// One todo.txt line per task
+pub fn write(tasks : &[Task]) -> String {
+    let mut s = String::new();
+
+    for task in tasks {
+        let mut words = Vec::new();
+        let mut created = task.created.and_then(timestamp_iso);
+
+        if task.done {
+            words.push(String::from("x"));
+            // The completion date has to come first when there is a creation date,
+            // without one the creation date would be read as the completion date
+            match task.completed.and_then(timestamp_iso) {
+                Some(completed) => words.push(completed),
+                None => created = None,
+            }
+        }
+        else if let Some(p) = priority_letter(task.priority) {
+            words.push(format!("({})", p));
+        }
+        if let Some(created) = created {
+            words.push(created);
+        }
+
+        words.push(String::from(task.name.trim()));
+        if !task.category.trim().is_empty() {
+            words.push(format!("+{}", tag(&task.category)));
+        }
+        if !task.sub_category.trim().is_empty() {
+            words.push(format!("@{}", tag(&task.sub_category)));
+        }
+        if let Some(due) = task.due.and_then(|d| d.to_iso()) {
+            words.push(format!("due:{}", due));
+        }
+        if let (true, Some(p)) = (task.done, priority_letter(task.priority)) {
+            words.push(format!("pri:{}", p));
+        }
+        if !task.id.is_empty() {
+            words.push(format!("id:{}", task.id));
+        }
+
+        s.push_str(&words.join(" "));
+        s.push('\n');
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn done_without_completion_date_has_no_dates() {
+        let mut task = Task::imported("a");
+        task.done = true;
+        task.created = Date::from_iso("2026-01-02").and_then(|d| d.timestamp());
+
+        let line = write(&[task]);
+        assert_eq!(line, "x a\n");
+        assert_eq!(parse(&line).unwrap()[0].completed, None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let day = |s| Date::from_iso(s).and_then(|d| d.timestamp());
+        let mut done = Task::imported("b");
+        done.id = String::from("2");
+        done.done = true;
+        done.priority = 2;
+        done.created = day("2026-01-02");
+        done.completed = day("2026-01-05");
+        let tasks = vec![
+            Task {
+                id : String::from("1"),
+                category : String::from("work"),
+                sub_category : String::from("mail"),
+                priority : 1,
+                due : Date::from_iso("2026-10-25"),
+                created : day("2026-01-01"),
+                ..Task::imported("a")
+            },
+            done,
+        ];
+
+        assert!(parse(&write(&tasks)).unwrap() == tasks);
+    }
+
+    #[test]
+    fn bad_due_dates_fail() {
+        assert!(parse("a due:2026-13-01").is_err());
+        assert!(parse("a due:2026-1-257").is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use toml::{Value, value::Table};
+
+/// The version of the toml layout this build writes, kept in `format_version` at the top of the file
+pub const FORMAT_VERSION : i64 = 1;
+
+/**
+    Each step upgrades the layout by one version and describes what it changed, files without
+    `format_version` are version 0. Version 1 only added `format_version`, so there is no step yet.
+*/
+const STEPS : [fn(&mut Table) -> Vec<String>; 0] = [];
+
+/// What upgrading a file did, or would do
+pub struct Upgrade {
+    pub from : i64,
+    pub changes : Vec<String>,
+}
+impl Upgrade {
+    pub fn needed(&self) -> bool {
+        self.from < FORMAT_VERSION
+    }
+}
+
+/**
+    Brings the toml of a tasks file up to `FORMAT_VERSION`, taking `format_version` out of it.
+    A file from a newer version is an error rather than something to guess at.
+*/
+pub fn upgrade(value : &mut Value) -> Result<Upgrade, String> {
+    let table = match value.as_table_mut() {
+        Some(t) => t,
+        None => return Err(String::from("expected a table at the top")),
+    };
+
+    let from = match table.remove("format_version") {
+        Some(Value::Integer(v)) if v >= 0 => v,
+        Some(v) => return Err(format!("invalid format_version {}, expected a positive number", v)),
+        None => 0,
+    };
+    if from > FORMAT_VERSION {
+        return Err(format!(
+            "format_version {} is newer than the {} this version of cool_organizer reads, update it",
+            from, FORMAT_VERSION
+        ));
+    }
+
+    let mut changes = Vec::new();
+    for (i, step) in STEPS.iter().enumerate().skip(from as usize) {
+        changes.extend(step(table).into_iter().map(|c| format!("{} -> {}: {}", i, i + 1, c)));
+    }
+
+    Ok(Upgrade { from, changes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text : &str) -> Value {
+        text.parse::<Value>().unwrap()
+    }
+
+    #[test]
+    fn upgrades_version_0() {
+        let mut value = parse("[[tasks]]\nname = \"a\"\n");
+        let upgrade = upgrade(&mut value).unwrap();
+
+        assert_eq!(upgrade.from, 0);
+        assert!(upgrade.needed());
+        assert_eq!(value, parse("[[tasks]]\nname = \"a\"\n"));
+    }
+
+    #[test]
+    fn takes_out_the_current_version() {
+        let mut value = parse(&format!("format_version = {}\n", FORMAT_VERSION));
+        let upgrade = upgrade(&mut value).unwrap();
+
+        assert_eq!(upgrade.from, FORMAT_VERSION);
+        assert!(!upgrade.needed());
+        assert!(upgrade.changes.is_empty());
+        assert!(value.get("format_version").is_none());
+    }
+
+    #[test]
+    fn refuses_a_newer_version() {
+        let mut value = parse(&format!("format_version = {}\n", FORMAT_VERSION + 1));
+        assert!(matches!(upgrade(&mut value), Err(e) if e.contains("newer")));
+    }
+
+    #[test]
+    fn rejects_a_negative_version() {
+        assert!(upgrade(&mut parse("format_version = -1\n")).is_err());
+        assert!(upgrade(&mut parse("format_version = \"1\"\n")).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::Task;
+
+/// Something wrong with a field of a task
+pub struct Issue {
+    pub field : &'static str,
+    pub problem : String,
+}
+impl Issue {
+    fn new(field : &'static str, problem : &str) -> Issue {
+        Issue {
+            field,
+            problem : String::from(problem),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} {}", self.field, self.problem)
+    }
+}
+
+/// What is wrong with a name or category, if anything
+pub fn text(text : &str) -> Option<&'static str> {
+    if text.chars().any(char::is_control) {
+        Some("has control characters like line breaks")
+    }
+    else if text.trim() != text {
+        Some("has spaces around it")
+    }
+    else {
+        None
+    }
+}
+
+fn text_issues(field : &'static str, value : &str, issues : &mut Vec<Issue>) {
+    if let Some(problem) = text(value) {
+        issues.push(Issue::new(field, problem));
+    }
+}
+
+/**
+    What is wrong with a task: an empty name, spaces around or control characters in the text fields,
+    a priority above 3 or a due date that isn't a real day. Nothing means the task is fine.
+*/
+pub fn issues(task : &Task) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if task.name.trim().is_empty() {
+        issues.push(Issue::new("name", "is empty"));
+    }
+    text_issues("name", &task.name, &mut issues);
+    text_issues("category", &task.category, &mut issues);
+    text_issues("sub category", &task.sub_category, &mut issues);
+
+    if task.priority > 3 {
+        issues.push(Issue { field : "priority", problem : format!("{} is above 3", task.priority) });
+    }
+    if let Some(due) = task.due.filter(|d| d.to_localdate().is_none()) {
+        issues.push(Issue { field : "due date", problem : format!("day {} of {} doesn't exist", due.day, due.year) });
+    }
+
+    issues
+}
+
+/**
+    Cleans up a text field. Older versions of the add dialog kept what was typed for the earlier
+    fields in the later ones, one line each, so the last non-empty line is what was meant for the
+    field. Other control characters become spaces and the spaces around it are trimmed.
+*/
+fn clean(text : &str) -> String {
+    let last = text.lines().map(str::trim).rfind(|l| !l.is_empty()).unwrap_or_default();
+
+    last.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/**
+    Repairs what `issues` finds where it can and describes each change. The priority is capped at 3
+    and a due date that doesn't exist is dropped, an empty name can't be repaired.
+*/
+pub fn fix(task : &mut Task) -> Vec<String> {
+    let mut fixed = Vec::new();
+
+    let fields = [
+        ("name", &mut task.name),
+        ("category", &mut task.category),
+        ("sub category", &mut task.sub_category),
+    ];
+    for (field, text) in fields {
+        let cleaned = clean(text);
+        if field == "name" && cleaned.is_empty() {
+            continue;
+        }
+        if cleaned != *text {
+            fixed.push(format!("{} {:?} is now {:?}", field, text, cleaned));
+            *text = cleaned;
+        }
+    }
+
+    if task.priority > 3 {
+        fixed.push(format!("priority {} is now 3", task.priority));
+        task.priority = 3;
+    }
+    if let Some(due) = task.due.filter(|d| d.to_localdate().is_none()) {
+        fixed.push(format!("due date (day {} of {}) was removed", due.day, due.year));
+        task.due = None;
+    }
+
+    fixed
+}